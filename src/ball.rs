@@ -1,42 +1,70 @@
-use super::paddle::Paddle;
-use super::WINDOW_SIZE;
+use super::paddle::{Paddle, Side};
+use super::{DT, WINDOW_SIZE};
 use ggez::graphics::{
     draw, BlendMode, Color, DrawMode, DrawParam, Drawable, MeshBuilder, Rect,
     BLACK,
 };
 use ggez::nalgebra::Point2;
 use ggez::{Context, GameResult};
-use rand::rngs::ThreadRng;
 use rand::Rng;
-
+use std::f32::consts::PI;
+
+// Velocities are in pixels per second. A ball goes at its velocity times its
+// pace, and the pace is the effects in force times the `rally` module's pace
+// for the hits so far times the handicap's ball speed. Bounces don't speed the
+// ball up. The velocity stays at `BASE_VELOCITY` except for two short-lived
+// terms: a swipe of the paddle or a bumper kicks the ball faster and a bounce
+// which isn't fully elastic slows it down. Either way the velocity gets back
+// by `DECREMENT_FACTOR` of the difference each tick.
 pub const RADIUS: f32 = 13.0;
-pub const INCREMENT_FACTOR: f32 = 1.0 / 500.0;
 pub const DECREMENT_FACTOR: f32 = 1.0 / 500.0;
 pub const RANDOM_BOUNCE_BOUND: f32 = 0.05;
-pub const WALL_ACCELERATION_BONUS: f32 = 1.4;
-pub const PADDLE_ACCELERATION_BONUS: f32 = 1.9;
-pub const MAX_ACCELERATION: f32 = 2.3;
-pub const MIN_ACCELERATION: f32 = -5.0;
-pub const MAX_VELOCITY: f32 = 6.0;
-pub const MIN_VELOCITY: f32 = 4.5;
+pub const BASE_VELOCITY: f32 = 300.0;
+
+/// The angle from the paddle's normal at which the ball leaves the paddle if
+/// it hits the very edge of the paddle.
+pub const MAX_BOUNCE_ANGLE: f32 = PI / 3.0;
+
+/// How many radians is the ball sent sideways per each pixel per second of
+/// the paddle's velocity.
+pub const ENGLISH_FACTOR: f32 = 0.0005;
+
+/// How many radians per second does the ball curve per each pixel per second
+/// of the paddle's velocity.
+pub const SPIN_FACTOR: f32 = 0.001;
+
+/// How much of its spin does the ball keep each tick.
+pub const SPIN_DECAY: f32 = 0.97;
+
+/// How much of the paddle's speed does the ball gain when it's hit.
+pub const SWIPE_FACTOR: f32 = 0.1;
+
+/// How many pixels per second at most does the ball gain in one swipe or one
+/// kick of a bumper. Nor does it go faster than this much over
+/// `BASE_VELOCITY`.
+pub const MAX_SWIPE_GAIN: f32 = 120.0;
 
+/// How many radians per second at most does a swipe spin the ball.
+pub const MAX_SPIN: f32 = 1.2;
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Ball {
     /// The x and y coordinate of the ball's center.
     pub center: (f32, f32),
 
+    /// Where was the ball's center before the last tick. The ball is drawn
+    /// in between the two positions if the frame comes in between ticks.
+    pub previous_center: (f32, f32),
+
     /// The ball size.
     pub radius: f32,
 
     /// The ball's colour which will be used to draw the ball in the next tick.
     pub color: Color,
 
-    /// How fast is the ball moving in its direction.
+    /// How fast is the ball moving in its direction, in pixels per second.
     velocity: f32,
 
-    /// The ball will gain one fifth of the current acceleration as velocity
-    /// each tick and the acceleration will decrease accordingly.
-    acceleration: f32,
-
     /// The direction vector. Both x and y are in interval <-1; 1>.
     /// ```
     ///        1|  v
@@ -49,150 +77,244 @@ pub struct Ball {
     /// ```
     /// In the example above, vector `v` = (0.5, 1). That means that in
     /// the next tick, its new position will be changed accordingly to
-    /// this direction scaled by the velocity.
+    /// this direction scaled by the velocity and the duration of a tick.
     direction: (f32, f32),
+
+    /// How fast does the ball's direction turn in radians per second. Positive
+    /// spin turns the ball clockwise.
+    spin: f32,
+
+    /// How many times faster than its velocity does the ball go, e.g. while
+    /// a power-up slows it down.
+    pub pace: f32,
+
+    /// Which player has hit the ball last, if any.
+    pub last_hit: Option<u8>,
 }
 
 impl Default for Ball {
     /// Some default values which are going to be changed with the update for ball skins.
     fn default() -> Self {
+        let center = (WINDOW_SIZE.0 / 2.0, WINDOW_SIZE.1 / 2.0);
         Ball {
-            center: (WINDOW_SIZE.0 / 2.0, WINDOW_SIZE.1 / 2.0),
+            center,
+            previous_center: center,
             radius: RADIUS,
-            velocity: 5.0,
-            acceleration: 0.0,
+            velocity: BASE_VELOCITY,
             direction: (1.0, 0.15),
+            spin: 0.0,
+            pace: 1.0,
+            last_hit: None,
             color: BLACK,
         }
     }
 }
 
 impl Ball {
-    /// First checks the balls position. If the ball gets close
-    /// to a wall, it bounces the ball off. Then based on current
-    /// acceleration increases or decreases ball speed.
-    pub fn tick(&mut self, rng: &mut ThreadRng) {
-        self.bounce_from_wall(rng);
-
-        // If the acceleration would slow down the ball, sets the acceleration
-        // bonus to zero and slowly starts decrementing the velocity.
-        if self.acceleration < 1.0 {
-            self.acceleration = 0.0;
-
-            self.velocity = MIN_VELOCITY
-                .max(self.velocity * DECREMENT_FACTOR)
-                .min(MAX_VELOCITY);
-        } else {
-            // Increments the velocity and decrements the acceleration.
-            let increment = self.acceleration * INCREMENT_FACTOR;
-            self.acceleration -= increment;
-            self.velocity += increment;
+    /// Brings the ball's velocity slowly back to `BASE_VELOCITY` after a kick
+    /// or a lossy bounce. The physics world then moves the ball.
+    pub fn tick(&mut self) {
+        self.previous_center = self.center;
+
+        self.velocity = (self.velocity
+            + (BASE_VELOCITY - self.velocity) * DECREMENT_FACTOR)
+            .min(BASE_VELOCITY + MAX_SWIPE_GAIN);
+
+        // The spin curves the ball's path and slowly wears off.
+        if self.spin != 0.0 {
+            let angle =
+                self.direction.1.atan2(self.direction.0) + self.spin * DT;
+            let (x, y) = (angle.cos(), angle.sin());
+            let max = x.abs().max(y.abs());
+            self.direction = (x / max, y / max);
+            self.spin *= SPIN_DECAY;
         }
-
-        self.center.0 += self.velocity * self.direction.0;
-        self.center.1 += self.velocity * self.direction.1;
     }
 
-    /// Checks whether the ball missed user paddle and hit
-    /// a wall perpendicular to the y axis. If so, returns
-    /// id of the player
+    /// Checks whether the ball missed user paddle and left the window. If
+    /// so, returns the edge it left through, which is the goal of the player
+    /// sitting by it.
     ///
     /// ```
-    /// | Player 0 |
-    /// |          |
-    /// | -------- |
-    /// |          |
-    /// | Player 1 |
+    /// |    Player 0     |
+    /// |                 |
+    /// | 2 ----------- 3 |
+    /// |                 |
+    /// |    Player 1     |
     /// ```
     ///
-    pub fn player_scored(&mut self) -> Option<u8> {
-        if self.center.1 >= WINDOW_SIZE.1 {
-            return Some(0);
+    pub fn left_through(&self) -> Option<Side> {
+        let (x, y) = self.center;
+        if y >= WINDOW_SIZE.1 {
+            Some(Side::Bottom)
+        } else if y <= 0.0 {
+            Some(Side::Top)
+        } else if x <= 0.0 {
+            Some(Side::Left)
+        } else if x >= WINDOW_SIZE.0 {
+            Some(Side::Right)
+        } else {
+            None
         }
+    }
 
-        if self.center.1 <= 0.0 {
-            return Some(1);
-        }
+    /// Returns the velocity vector of the ball in pixels per second,
+    /// including its pace.
+    pub fn velocity(&self) -> (f32, f32) {
+        let velocity = self.velocity * self.pace;
+        (velocity * self.direction.0, velocity * self.direction.1)
+    }
 
-        None
+    /// Moves the ball to given position and sets its velocity vector in
+    /// pixels per second, e.g. after the physics world moved it.
+    pub fn follow(&mut self, center: (f32, f32), (x, y): (f32, f32)) {
+        self.center = center;
+        let max = x.abs().max(y.abs());
+        if max > 0.0 {
+            self.velocity = max / self.pace;
+            self.direction = (x / max, y / max);
+        }
     }
 
-    /// Checks whether the ball should bounce from a wall
-    /// parallel to the y axis.
-    pub fn bounce_from_wall(&mut self, rng: &mut ThreadRng) {
-        // If the ball is touching or is beyond the right wall and its direction is to the right as
-        // well (positive x value of the direction vector), then the ball should bounce.
-        let bounces_off: bool = self.center.0 + self.radius >= WINDOW_SIZE.0
-            && self.direction.0 >= 0.0;
+    /// Sends the ball which was about to leave the playfield through given
+    /// edge back where it came from, e.g. off a shield.
+    pub fn rebound(&mut self, side: Side) {
+        let across = side
+            .across(self.center)
+            .max(self.radius)
+            .min(side.across(WINDOW_SIZE) - self.radius);
+        self.center = side.point(side.along(self.center), across);
+        let (along, across) =
+            (side.along(self.direction), side.across(self.direction));
+        self.direction = side.point(along, -across);
+    }
 
-        // Similar check is applied for the left wall.
-        let bounces_off: bool = bounces_off
-            || (self.center.0 - self.radius <= 0.0 && self.direction.0 <= 0.0);
+    /// Returns the ball as seen by the player at given edge, turned so that
+    /// their edge is the top or the bottom one and the coordinates along it
+    /// stay the same. Controllers only know how to play from the top and the
+    /// bottom edge.
+    pub fn seen_from(&self, side: Side) -> Self {
+        if !side.is_vertical() {
+            return self.clone();
+        }
 
-        if bounces_off {
-            // Bounces off a vertical collider - a wall.
-            self.bounce((-1.0, 1.0), WALL_ACCELERATION_BONUS, rng);
+        // The right edge turns into the bottom one.
+        let shift = if side == Side::Right {
+            WINDOW_SIZE.0 - WINDOW_SIZE.1
+        } else {
+            0.0
+        };
+        let turn = |(x, y): (f32, f32)| (y, x - shift);
+        Self {
+            center: turn(self.center),
+            previous_center: turn(self.previous_center),
+            direction: (self.direction.1, self.direction.0),
+            spin: -self.spin,
+            ..self.clone()
         }
     }
 
-    pub fn bounce_from_paddle(&mut self, paddle: &Paddle, rng: &mut ThreadRng) {
-        if paddle.player_id == 0 && self.direction.1 > 0.0 {
-            return;
-        }
-        if paddle.player_id == 1 && self.direction.1 < 0.0 {
-            return;
+    /// Returns the ball seen by the player at given edge, see `seen_from`,
+    /// as if their edge was flipped end to end.
+    pub fn mirrored(&self, side: Side) -> Self {
+        let length = side.length();
+        Self {
+            center: (length - self.center.0, self.center.1),
+            previous_center: (
+                length - self.previous_center.0,
+                self.previous_center.1,
+            ),
+            direction: (-self.direction.0, self.direction.1),
+            spin: -self.spin,
+            ..self.clone()
         }
+    }
 
-        let (left_x, top_y) = paddle.position();
-        let right_x = left_x + paddle.width;
-        let bottom_y = top_y + paddle.height;
+    /// Returns the direction vector the ball is moving in.
+    pub fn direction(&self) -> (f32, f32) {
+        self.direction
+    }
 
-        if self.contains(left_x, top_y)
-            || self.contains(right_x, top_y)
-            || self.contains(left_x, bottom_y)
-            || self.contains(right_x, bottom_y)
-        {
-            return self.bounce((-1.0, -1.0), PADDLE_ACCELERATION_BONUS, rng);
+    /// Predicts at which x coordinate will the ball's center cross the line
+    /// given by the y coordinate, including the bounces off the walls. Returns
+    /// `None` if the ball is moving away from the line.
+    pub fn predict_x(&self, y: f32) -> Option<f32> {
+        let ticks = (y - self.center.1) / self.direction.1;
+        if !ticks.is_finite() || ticks < 0.0 {
+            return None;
         }
 
-        // If the paddle is player one's, but the ball is above the paddle, return,
-        if paddle.player_id == 0 && self.center.1 - self.radius > bottom_y {
-            return;
-        }
+        // Unfolds the walls into a sequence of mirrored playfields. The ball
+        // flies straight through them and each odd playfield is mirrored.
+        let width = WINDOW_SIZE.0 - 2.0 * self.radius;
+        let x = self.center.0 + self.direction.0 * ticks - self.radius;
+        let x = x.rem_euclid(2.0 * width);
+        Some(self.radius + if x > width { 2.0 * width - x } else { x })
+    }
 
-        // If the paddle is player two's, but the ball is below the paddle, return.
-        if paddle.player_id == 1 && self.center.1 + self.radius < top_y {
-            return;
-        }
+    /// Sends the ball back from the paddle at an angle given by where it hit
+    /// the paddle. The ball goes straight if it hits the center and the
+    /// closer to an edge it hits, the steeper it goes. A moving paddle sends
+    /// the ball further in the direction it moves, speeds it up and puts a
+    /// spin on it which curves the ball the same way.
+    pub fn aim(&mut self, paddle: &Paddle) {
+        let side = paddle.side;
+        let reach = paddle.width / 2.0 + self.radius;
+        let offset = ((side.along(self.center) - paddle.x) / reach)
+            .max(-1.0)
+            .min(1.0);
+        let angle = (offset * MAX_BOUNCE_ANGLE
+            + paddle.velocity * ENGLISH_FACTOR)
+            .max(-MAX_BOUNCE_ANGLE)
+            .min(MAX_BOUNCE_ANGLE);
+        let away = side.away();
+        // The left and the right edge are the top and the bottom one
+        // mirrored along the diagonal, which turns the spin the other way.
+        let turn = if side.is_vertical() { -1.0 } else { 1.0 };
+        self.last_hit = Some(paddle.player_id);
+        self.spin = (-away * turn * paddle.velocity * SPIN_FACTOR)
+            .max(-MAX_SPIN)
+            .min(MAX_SPIN);
+        self.kick(paddle.velocity.abs() * SWIPE_FACTOR);
+        self.head(side, angle, away);
+    }
 
-        if self.center.0 + self.radius >= left_x
-            && self.center.0 - self.radius <= right_x
-        {
-            return self.bounce((1.0, -1.0), PADDLE_ACCELERATION_BONUS, rng);
-        }
+    /// Speeds the ball up by given pixels per second, at most by
+    /// `MAX_SWIPE_GAIN`. The kick wears off in the following ticks.
+    pub fn kick(&mut self, gain: f32) {
+        self.velocity = (self.velocity + gain.max(0.0).min(MAX_SWIPE_GAIN))
+            .min(BASE_VELOCITY + MAX_SWIPE_GAIN);
     }
 
-    /// Applies transformation to ball's direction vector. The new direction vector is always
-    /// in interval <-1; 1> for both x and y.
-    pub fn bounce(
-        &mut self,
-        (x, y): (f32, f32),
-        accelerate: f32,
-        rng: &mut ThreadRng,
-    ) {
-        // Increases acceleration but keeps it between thresholds. The ball will gain N of its
-        // acceleration as velocity every tick.
-        self.acceleration = (self.acceleration + accelerate)
-            .min(MAX_ACCELERATION)
-            .max(MIN_ACCELERATION);
+    /// Sends the ball towards given player at given angle from the straight
+    /// line to their edge.
+    pub fn serve(&mut self, player_id: u8, angle: f32) {
+        let side = Side::of(player_id);
+        self.head(side, angle, -side.away());
+    }
 
+    // Points the ball at given angle from the line across given edge, where
+    // the sign of across says whether it goes away from the edge or towards
+    // it.
+    fn head(&mut self, side: Side, angle: f32, across: f32) {
+        let (x, y) = side.point(angle.sin(), across * angle.cos());
+        let max = x.abs().max(y.abs());
+        self.direction = (x / max, y / max);
+    }
+
+    /// Nudges the ball's direction by at most given noise after it bounced.
+    /// The new direction vector is always in interval <-1; 1> for both x
+    /// and y.
+    pub fn bounce(&mut self, noise: f32, rng: &mut impl Rng) {
         // Small nudge in a random direction.
-        let random_bounce =
-            rng.gen_range(-RANDOM_BOUNCE_BOUND, RANDOM_BOUNCE_BOUND);
+        let random_bounce = if noise > 0.0 {
+            rng.gen_range(-noise, noise)
+        } else {
+            0.0
+        };
 
-        // Applies the transformation vector.
-        let new_x = self.direction.0 * x + random_bounce;
-        let new_y = self.direction.1 * y + random_bounce;
+        let new_x = self.direction.0 + random_bounce;
+        let new_y = self.direction.1 + random_bounce;
 
         // Puts both direction in positive and selects the greater one.
         let max = new_x.abs().max(new_y.abs());
@@ -201,12 +323,6 @@ impl Ball {
         self.direction.0 = new_x / max;
         self.direction.1 = new_y / max;
     }
-
-    /// Whether the ball contains given point.
-    pub fn contains(&self, x: f32, y: f32) -> bool {
-        (x - self.center.0).powi(2) + (y - self.center.1).powi(2)
-            <= self.radius.powi(2)
-    }
 }
 
 impl Drawable for Ball {
@@ -242,3 +358,182 @@ impl Drawable for Ball {
         Some(BlendMode::Replace)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_aim_by_hit_position() {
+        let paddle = Paddle::new(1);
+        let mut ball = Ball::default();
+
+        // The center sends the ball straight back.
+        ball.center.0 = paddle.x;
+        ball.aim(&paddle);
+        assert!(ball.direction.0.abs() < 0.01);
+        assert!((ball.direction.1 + 1.0).abs() < 0.01);
+
+        // The left half sends it to the left, the further the steeper.
+        ball.center.0 = paddle.x - 20.0;
+        ball.aim(&paddle);
+        let (x, y) = ball.direction;
+        assert!(x < 0.0 && y < 0.0);
+        ball.center.0 = paddle.x - paddle.width / 2.0;
+        ball.aim(&paddle);
+        assert!(ball.direction.0 < x);
+
+        // The edge sends the ball at the steepest angle, which is the same
+        // beyond the edge.
+        let angle = |ball: &Ball| ball.direction.0.atan2(-ball.direction.1);
+        ball.center.0 = paddle.x + paddle.width / 2.0 + RADIUS;
+        ball.aim(&paddle);
+        assert!((angle(&ball) - MAX_BOUNCE_ANGLE).abs() < 0.01);
+        ball.center.0 += 10.0;
+        ball.aim(&paddle);
+        assert!((angle(&ball) - MAX_BOUNCE_ANGLE).abs() < 0.01);
+
+        // The first player's paddle sends the ball down.
+        ball.aim(&Paddle::new(0));
+        assert!(ball.direction.1 > 0.0);
+    }
+
+    #[test]
+    fn test_serve() {
+        let mut ball = Ball::default();
+        ball.serve(0, 0.0);
+        assert!(ball.direction.0.abs() < 0.01);
+        assert!((ball.direction.1 + 1.0).abs() < 0.01);
+
+        ball.serve(1, -0.3);
+        assert!(ball.direction.0 < 0.0);
+        assert!((ball.direction.1 - 1.0).abs() < 0.01);
+
+        ball.serve(2, 0.0);
+        assert!((ball.direction.0 + 1.0).abs() < 0.01);
+        assert!(ball.direction.1.abs() < 0.01);
+        ball.serve(3, 0.3);
+        assert!((ball.direction.0 - 1.0).abs() < 0.01);
+        assert!(ball.direction.1 > 0.0);
+    }
+
+    #[test]
+    fn test_sides() {
+        // The upper half of the right paddle sends the ball up and left.
+        let paddle = Paddle::new(3);
+        let mut ball = Ball::default();
+        ball.center = (paddle.ball_line(RADIUS), paddle.x - 20.0);
+        ball.aim(&paddle);
+        let (x, y) = ball.direction;
+        assert!(x < 0.0 && y < 0.0);
+
+        // The right player sees the ball coming from above at their edge,
+        // which is the bottom one, at the same point along it.
+        let seen = ball.seen_from(Side::Right);
+        assert!((seen.center.0 - ball.center.1).abs() < 0.01);
+        assert!((seen.center.1 - paddle.seen().ball_line(RADIUS)).abs() < 0.01);
+        assert!(seen.direction().1 < 0.0);
+
+        // Reversed controls flip the right edge end to end.
+        let mirrored = seen.mirrored(Side::Right);
+        assert!(
+            (mirrored.center.0 - (WINDOW_SIZE.1 - seen.center.0)).abs() < 0.01
+        );
+        assert!(mirrored.direction().0 * seen.direction().0 <= 0.0);
+
+        ball.center = (-1.0, 300.0);
+        assert_eq!(Some(Side::Left), ball.left_through());
+        ball.rebound(Side::Left);
+        assert!((ball.center.0 - RADIUS).abs() < 0.01);
+        assert!(ball.direction.0 > 0.0);
+        assert_eq!(None, ball.left_through());
+    }
+
+    #[test]
+    fn test_speed_decays_slowly() {
+        let mut ball = Ball::default();
+        ball.kick(100.0);
+        ball.tick();
+        let decayed = BASE_VELOCITY + 100.0 * (1.0 - DECREMENT_FACTOR);
+        assert!((ball.velocity - decayed).abs() < 0.01);
+
+        for _ in 0..5000 {
+            ball.tick();
+        }
+        assert!((ball.velocity - BASE_VELOCITY).abs() < 0.01);
+
+        // Bounces don't speed the ball up, a ball slowed down by a bounce
+        // gets back to its speed.
+        let mut rng = StdRng::seed_from_u64(0);
+        ball.bounce(0.0, &mut rng);
+        ball.tick();
+        assert!((ball.velocity - BASE_VELOCITY).abs() < 0.01);
+        ball.velocity = BASE_VELOCITY / 2.0;
+        for _ in 0..5000 {
+            ball.tick();
+        }
+        assert!((ball.velocity - BASE_VELOCITY).abs() < 0.01);
+
+        // Bumpers kick the ball at most as much as swipes.
+        ball.kick(1000.0);
+        assert!((ball.velocity - BASE_VELOCITY - MAX_SWIPE_GAIN).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_swipe_sends_ball_sideways_faster_and_curves_it() {
+        let mut paddle = Paddle::new(1);
+        let mut ball = Ball::default();
+        ball.center.0 = paddle.x;
+        paddle.velocity = 600.0;
+        ball.aim(&paddle);
+        assert!(ball.direction.0 > 0.1);
+        assert!(ball.direction.1 < 0.0);
+        assert!(ball.velocity > 300.0 + 50.0);
+
+        // The ball moves up and curves further to the right.
+        let (x, _) = ball.direction;
+        ball.tick();
+        assert!(ball.direction.0 > x);
+        for _ in 0..200 {
+            ball.tick();
+        }
+        assert!(ball.spin.abs() < 0.01);
+
+        // Swiping the other way curves the ball the other way.
+        paddle.velocity = -600.0;
+        ball.aim(&paddle);
+        let (x, _) = ball.direction;
+        ball.tick();
+        assert!(ball.direction.0 < x);
+    }
+
+    #[test]
+    fn test_teleporting_paddle_swipes_within_limits() {
+        // The paddle jumps from one end of its edge to the other in a tick.
+        let mut paddle = Paddle::new(1);
+        paddle.move_to(0.0);
+        paddle.previous_x = paddle.x;
+        paddle.move_to(WINDOW_SIZE.0);
+        paddle.track_velocity();
+
+        let mut ball = Ball::default();
+        ball.kick(MAX_SWIPE_GAIN);
+        ball.center.0 = paddle.x;
+        ball.aim(&paddle);
+        assert!(ball.velocity <= BASE_VELOCITY + MAX_SWIPE_GAIN);
+        assert!(ball.spin.abs() <= MAX_SPIN);
+
+        // Neither does the ball get any faster afterwards, and it slows
+        // down gradually.
+        let mut previous = ball.velocity;
+        for _ in 0..1000 {
+            ball.tick();
+            assert!(ball.velocity <= BASE_VELOCITY + MAX_SWIPE_GAIN);
+            assert!(previous - ball.velocity < 1.0);
+            previous = ball.velocity;
+        }
+        assert!(ball.velocity < BASE_VELOCITY + MAX_SWIPE_GAIN / 2.0);
+    }
+}
@@ -1,5 +1,10 @@
+use super::markers::{self, normalize_angle, Marker};
+use super::paddle::Side;
 use super::WINDOW_SIZE;
+use std::env;
+use std::f32::consts::PI;
 use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
@@ -7,13 +12,237 @@ use std::thread::JoinHandle;
 const CAMERA_DEV: &str = "/dev/video2";
 const FORMAT: &[u8] = b"RGB3";
 const MINIMUM_BACKGROUND_DISTANCE: u8 = 10;
-const WINDOW_WIDTH: u32 = WINDOW_SIZE.0 as u32;
+
+/// A snapshot of the playfield is only good if at least this fraction of
+/// its columns look like the empty table.
+const MIN_UNIFORMITY: f32 = 0.95;
+
+/// How many columns around a column does the empty table look like. Light
+/// falls on the table unevenly, so each column is only compared to those
+/// around it.
+const UNIFORMITY_WINDOW: usize = 320;
+
+/// The part of the table where the camera looks for a player's hand, a strip
+/// along the player's edge. Coordinates are in pixels of the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Zone {
+    /// Which edge does the strip lie along.
+    pub side: Side,
+
+    /// How far from the edge does the strip start and end.
+    pub depth: (f32, f32),
+
+    /// Where along the edge does the strip start and end. The hand moves the
+    /// paddle along the whole edge, however long the strip is.
+    pub along: (f32, f32),
+}
+
+impl Zone {
+    // Returns the average gray of each line of the frame of given resolution
+    // across the strip, in the order of the coordinate along the edge. The
+    // camera takes a mirror image rotated by 180 degrees, hence the frame is
+    // read backwards.
+    fn profile(
+        &self,
+        frame: &[u8],
+        (width, height): (usize, usize),
+    ) -> Vec<u8> {
+        let (x, y) = self.rectangle();
+        let column = |x: f32| {
+            (((WINDOW_SIZE.0 - x) * width as f32 / WINDOW_SIZE.0) as usize)
+                .min(width)
+        };
+        let row = |y: f32| {
+            (((WINDOW_SIZE.1 - y) * height as f32 / WINDOW_SIZE.1) as usize)
+                .min(height)
+        };
+        let (columns, rows) = (column(x.1)..column(x.0), row(y.1)..row(y.0));
+
+        let gray = |col: usize, row: usize| {
+            let i = (row * width + col) * 3;
+            u32::from(grayscale(&frame[i..i + 3]))
+        };
+        let average = |grays: &mut dyn Iterator<Item = u32>| {
+            let (sum, count) = grays
+                .fold((0, 0), |(sum, count), gray| (sum + gray, count + 1));
+            (sum / count.max(1)) as u8
+        };
+        if self.side.is_vertical() {
+            rows.rev()
+                .map(|row| {
+                    average(&mut columns.clone().map(|col| gray(col, row)))
+                })
+                .collect()
+        } else {
+            columns
+                .rev()
+                .map(|col| average(&mut rows.clone().map(|row| gray(col, row))))
+                .collect()
+        }
+    }
+
+    // Returns the x and the y range of the window the strip covers.
+    fn rectangle(&self) -> ((f32, f32), (f32, f32)) {
+        let across = |depth: f32| {
+            if self.side.away() > 0.0 {
+                depth
+            } else {
+                self.side.across(WINDOW_SIZE) - depth
+            }
+        };
+        let (from_x, from_y) =
+            self.side.point(self.along.0, across(self.depth.0));
+        let (to_x, to_y) = self.side.point(self.along.1, across(self.depth.1));
+        (
+            (from_x.min(to_x), from_x.max(to_x)),
+            (from_y.min(to_y), from_y.max(to_y)),
+        )
+    }
+
+    /// Splits the strip into the back half by the edge and the front half
+    /// farther from it, so that two players of a team can share it.
+    pub fn split(self) -> (Zone, Zone) {
+        let middle = (self.depth.0 + self.depth.1) / 2.0;
+        (
+            Zone {
+                depth: (self.depth.0, middle),
+                ..self
+            },
+            Zone {
+                depth: (middle, self.depth.1),
+                ..self
+            },
+        )
+    }
+
+    // Converts a position given as a fraction of the strip's length into the
+    // position of the paddle along its edge.
+    fn position(&self, fraction: f32) -> u32 {
+        (fraction * self.side.length()) as u32
+    }
+}
+
+/// Lays out the zones of players who sit by given edges, in the order of the
+/// players. Two players by the top and the bottom edge split the table in
+/// halves. Players by the left and the right edge take a quarter of the
+/// width each and the others share the middle of their halves.
+pub fn zones(sides: &[Side]) -> Vec<Zone> {
+    let sideways = sides.iter().any(|side| side.is_vertical());
+    let margin = if sideways { WINDOW_SIZE.0 / 4.0 } else { 0.0 };
+    sides
+        .iter()
+        .map(|side| {
+            if side.is_vertical() {
+                Zone {
+                    side: *side,
+                    depth: (0.0, margin),
+                    along: (0.0, side.length()),
+                }
+            } else {
+                Zone {
+                    side: *side,
+                    depth: (0.0, WINDOW_SIZE.1 / 2.0),
+                    along: (margin, side.length() - margin),
+                }
+            }
+        })
+        .collect()
+}
+
+/// Which objects does the camera look for in its frames.
+#[derive(Clone, Debug)]
+pub enum Tracking {
+    /// Finds the columns of each half of the playfield which differ the most
+    /// from the background. Good enough for a hand or a pen.
+    Columns,
+
+    /// Finds printed square markers, see the `markers` module. Value at index
+    /// `i` is the id of the marker which moves the paddle of player `i`. The
+    /// markers tell apart a car from a hand and may share a half. A marker
+    /// moves its paddle to where it is along the player's edge, hence they
+    /// also track the players by the left and the right edge.
+    Markers(Vec<u8>),
+}
+
+impl Tracking {
+    /// Reads the tracking method from `PONG_TRACKING` env var, which is either
+    /// "columns" (default) or "markers". Markers are bound to the players by
+    /// `PONG_MARKERS` env var, e.g. "3,7" means player 0 is tracked by marker
+    /// with id 3 and player 1 by marker with id 7. Up to four markers can be
+    /// bound.
+    pub fn from_env() -> Self {
+        match env::var("PONG_TRACKING").as_ref().map(String::as_str) {
+            Err(_) | Ok("columns") => Tracking::Columns,
+            Ok("markers") => {
+                let bindings = env::var("PONG_MARKERS")
+                    .expect("PONG_MARKERS must be set to track markers");
+                let ids: Vec<u8> = bindings
+                    .split(',')
+                    .map(|id| id.trim().parse().expect("Invalid marker id"))
+                    .collect();
+                assert!(
+                    ids.len() >= 2 && ids.len() <= 4,
+                    "Bind one marker to each player"
+                );
+                Tracking::Markers(ids)
+            }
+            Ok(other) => panic!("Unknown tracking method {}", other),
+        }
+    }
+}
+
+/// How good is a snapshot of the playfield to tell objects on the table
+/// apart from it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Calibration {
+    /// Fraction of columns which look like the empty table in the half
+    /// which looks worse.
+    pub uniformity: f32,
+
+    /// Average gray of each column of the top and of the bottom half.
+    pub profile: [Vec<u8>; 2],
+}
+
+impl Calibration {
+    fn new(top_half: &[u8], bottom_half: &[u8]) -> Self {
+        Self {
+            uniformity: uniformity(top_half).min(uniformity(bottom_half)),
+            profile: [top_half.to_vec(), bottom_half.to_vec()],
+        }
+    }
+
+    /// Whether the table was clear when the snapshot was taken. If a hand
+    /// was in view, the snapshot should be taken again.
+    pub fn is_clear(&self) -> bool {
+        self.uniformity >= MIN_UNIFORMITY
+    }
+}
 
 /// Object used for scanning the camera input. It updates controller positions.
 pub struct Camera {
-    /// Latest position of each player. This value is updated by the camera.
+    /// Latest position of each player along their edge. This value is
+    /// updated by the camera.
     /// TODO: Consider making these atomic u32s.
-    pub positions: [Arc<Mutex<u32>>; 2],
+    pub positions: Vec<Arc<Mutex<u32>>>,
+
+    /// Markers seen in the latest frame, in window coordinates. This value is
+    /// updated by the camera if it tracks markers.
+    pub markers: Arc<Mutex<Vec<Marker>>>,
+
+    /// Set to ask the camera thread to map the playfield again, e.g. when
+    /// the light has changed. The camera clears it once it's done.
+    pub remap: Arc<AtomicBool>,
+
+    // What does the camera look for in the frames.
+    tracking: Tracking,
+
+    // Where does the camera look for each player's hand, along which edge
+    // does their paddle move.
+    zones: Vec<Zone>,
+
+    // What did each player's zone look like when the game started, see
+    // `Zone::profile`.
+    backgrounds: Vec<Vec<u8>>,
 
     // Handle to the started camera which can capture images.
     handle: rscam::Camera,
@@ -28,14 +257,15 @@ pub struct Camera {
 }
 
 impl Camera {
-    /// Builds a new empty camera that has be to calibrated.
-    pub fn new() -> Self {
+    /// Builds a new empty camera that has be to calibrated for players who
+    /// play in given zones, see `zones`. Fails if there's no camera device.
+    pub fn new(tracking: Tracking, zones: Vec<Zone>) -> io::Result<Self> {
         info!(
-            "Starting camera in format {}",
-            String::from_utf8_lossy(&FORMAT)
+            "Starting camera in format {} tracking {:?}",
+            String::from_utf8_lossy(&FORMAT),
+            tracking
         );
-        let mut handle =
-            rscam::new(CAMERA_DEV).expect("Cannot find camera device");
+        let mut handle = rscam::new(CAMERA_DEV)?;
 
         for format in handle.formats() {
             let format = format.expect("Cannot read format");
@@ -60,22 +290,29 @@ impl Camera {
         handle.capture().expect("Cannot take a capture");
 
         // Some values will be calibrated later.
-        Self {
-            positions: [
-                Arc::new(Mutex::new(WINDOW_WIDTH / 2)),
-                Arc::new(Mutex::new(WINDOW_WIDTH / 2)),
-            ],
+        Ok(Self {
+            positions: zones
+                .iter()
+                .map(|zone| {
+                    Arc::new(Mutex::new((zone.side.length() / 2.0) as u32))
+                })
+                .collect(),
+            markers: Arc::new(Mutex::new(Vec::new())),
+            remap: Arc::new(AtomicBool::new(false)),
+            tracking,
+            backgrounds: vec![Vec::new(); zones.len()],
+            zones,
             handle,
             top_half_bg: [0; 1280],
             bottom_half_bg: [0; 1280],
-        }
+        })
     }
 
     /// Captures the empty playfield to learn about its default colours and
     /// inconsistencies. It records this default state and when the camera
     /// thread starts updating the positions, it will calculate them against
-    /// this default.
-    pub fn map_playfield(&mut self) {
+    /// this default. Returns how good the snapshot is.
+    pub fn map_playfield(&mut self) -> Calibration {
         // Capture the visible field.
         let frame = self.handle.capture().expect("Cannot capture camera input");
 
@@ -86,56 +323,109 @@ impl Camera {
             &mut self.top_half_bg,
             &mut self.bottom_half_bg,
         );
+        let (width, height) = frame.resolution;
+        let resolution = (width as usize, height as usize);
+        self.backgrounds = self
+            .zones
+            .iter()
+            .map(|zone| zone.profile(&frame, resolution))
+            .collect();
+
+        Calibration::new(&self.top_half_bg, &self.bottom_half_bg)
     }
 
     /// Starts a new thread on which the camera continuously screens the
     /// playfield and update the paddle positions.
-    pub fn start_capturing(self) -> io::Result<JoinHandle<()>> {
+    pub fn start_capturing(mut self) -> io::Result<JoinHandle<()>> {
         info!("Starting new thread for camera.");
         let camera_thread = thread::Builder::new().name("camera".to_string());
-        camera_thread.spawn(move || {
-            let mut top_half = [0; 1280];
-            let mut bottom_half = [0; 1280];
-            loop {
-                let frame =
-                    self.handle.capture().expect("Cannot capture camera input");
-                average_gray_for_frame_halves(
-                    &frame,
-                    &mut top_half,
-                    &mut bottom_half,
-                );
-
-                // Finds both controllers in their respective halves and returns
-                // their position on the x axis.
-                let top_x =
-                    self.find_controller(&self.top_half_bg, &mut top_half);
-                // let bottom_x =
-                // self.ind_controller(&self.bottom_half_bg, &mut bottom_half);
-
-                // Updates the controllers.
-                for x in top_x {
-                    println!("Updating controller 1 to {}", x);
-                    (*self.positions[1].lock().unwrap()) = x;
+        camera_thread.spawn(move || loop {
+            if self.remap.swap(false, Ordering::SeqCst) {
+                info!("Taking a new snapshot of the playfield.");
+                let calibration = self.map_playfield();
+                if !calibration.is_clear() {
+                    warn!(
+                        "The table wasn't clear, only {:.0}% of it is",
+                        calibration.uniformity * 100.0
+                    );
                 }
-                // for x in bottom_x {
-                //     println!("Updating controller 2 to {}", x);
-                //     (*self.positions[1].lock().unwrap()) = x;
-                // }
+            }
+
+            let frame =
+                self.handle.capture().expect("Cannot capture camera input");
+            match &self.tracking {
+                Tracking::Columns => self.track_columns(&frame),
+                Tracking::Markers(ids) => self.track_markers(&frame, ids),
             }
         })
     }
+
+    // Finds each player's controller in their zone and updates its position
+    // along their edge. Players whose zone looks like the empty table keep
+    // their last known position.
+    fn track_columns(&self, frame: &rscam::Frame) {
+        let (width, height) = frame.resolution;
+        let resolution = (width as usize, height as usize);
+        let zones = self.zones.iter().zip(&self.backgrounds);
+        for (player, (zone, background)) in zones.enumerate() {
+            let position = &self.positions[player];
+            let mut profile = zone.profile(frame, resolution);
+            if profile.is_empty() || profile.len() != background.len() {
+                continue;
+            }
+
+            if let Some(fraction) =
+                self.find_controller(background, &mut profile)
+            {
+                let x = zone.position(fraction);
+                trace!("Updating controller {} to {}", player, x);
+                (*position.lock().unwrap()) = x;
+            }
+        }
+    }
+
+    // Finds all markers in the frame and moves the players whose markers are
+    // visible to where the markers are along their edges. Other players keep
+    // their last known position.
+    fn track_markers(&self, frame: &rscam::Frame, ids: &[u8]) {
+        let (width, height) = frame.resolution;
+        let markers: Vec<_> =
+            markers::detect(frame, width as usize, height as usize)
+                .into_iter()
+                .map(|marker| to_window(marker, width, height))
+                .collect();
+
+        for marker in &markers {
+            trace!(
+                "Marker {} at {:?} heading {}",
+                marker.id,
+                marker.position,
+                marker.heading
+            );
+            let bound = ids.iter().zip(&self.positions).zip(&self.zones);
+            for ((id, position), zone) in bound {
+                if marker.id == *id {
+                    (*position.lock().unwrap()) =
+                        zone.side.along(marker.position) as u32;
+                }
+            }
+        }
+
+        (*self.markers.lock().unwrap()) = markers;
+    }
+
     // Calculates a diff between the "background", that is the state of the
     // playfield when the game started and there were no objects, which we know
     // from the "maps_playfield" phase, and the current frame. If we find
     // sequence of columns which have increased difference, or distance, to the
     // background, we mark those columns as candidates for having the controller
-    // positioned there. Returns controller's position relative to the window,
-    // not to the width of the frame.
+    // positioned there. Returns controller's position as a fraction of the
+    // length of the frame.
     fn find_controller(
         &self,
         background: &[u8],
         frame: &mut [u8],
-    ) -> Option<u32> {
+    ) -> Option<f32> {
         debug_assert_eq!(background.len(), frame.len());
         // Calculates the distance from the background and removes mutability.
         distance_from_background(background, frame);
@@ -208,14 +498,26 @@ impl Camera {
         // long and it starts at x = 30, then we return 45.
         best_streak
             .map(|(from, to)| (to - from) / 2 + from)
-            // Calculates the position of the controller relative to the window
-            // width. If the camera input is 1280px and the window is 500 px
-            // wide then a controller at 640px of camera input should be
-            // positioned to 250px of window.
-            .map(|x| x * WINDOW_WIDTH as usize / frame.len())
-            // To accommodate the fact that camera takes mirror image, we move
-            // the x coordinate to the other side.
-            .map(|x| WINDOW_WIDTH - x as u32)
+            // Calculates the position of the controller relative to the length
+            // of the frame. If the frame is 1280 columns long, a controller at
+            // the 640th column is in the middle.
+            .map(|x| x as f32 / frame.len() as f32)
+    }
+}
+
+// Converts a marker found in a frame of given resolution into the window
+// coordinates. The camera takes a mirror image and the top half of the frame
+// is the bottom half of the window, therefore the frame is rotated by 180
+// degrees.
+fn to_window(marker: Marker, width: u32, height: u32) -> Marker {
+    let (x, y) = marker.position;
+    Marker {
+        position: (
+            WINDOW_SIZE.0 - x * WINDOW_SIZE.0 / width as f32,
+            WINDOW_SIZE.1 - y * WINDOW_SIZE.1 / height as f32,
+        ),
+        heading: normalize_angle(marker.heading + PI),
+        ..marker
     }
 }
 
@@ -260,18 +562,9 @@ fn calculate_average_column_gray(frame: &[u8], averages_store: &mut [u8]) {
 
     // Each pixel is represented by 3 bytes, red green and blue.
     for pixels in frame.chunks(3) {
-        // Calculates the grayscale from RGB. Preferably we would get the
-        // grayscale from the camera input. However this seems HW dependent.
-        let grayscale = {
-            let r = pixels[0] / 10 * 3;
-            let g = pixels[1] / 10 * 6;
-            let b = pixels[2] / 10;
-            r + g + b
-        };
-
         // Adds the grayscale value of the pixel to the average of the column
         // it belongs to.
-        cols[col] += grayscale as f32 / col_pixels_count as f32;
+        cols[col] += f32::from(grayscale(pixels)) / col_pixels_count as f32;
 
         // Increments col until it reaches end of a row.
         col = if col == cols.len() - 1 { 0 } else { col + 1 };
@@ -283,6 +576,43 @@ fn calculate_average_column_gray(frame: &[u8], averages_store: &mut [u8]) {
     }
 }
 
+/// Calculates the grayscale from an RGB pixel. Preferably we would get the
+/// grayscale from the camera input. However this seems HW dependent.
+pub fn grayscale(pixel: &[u8]) -> u8 {
+    let r = pixel[0] / 10 * 3;
+    let g = pixel[1] / 10 * 6;
+    let b = pixel[2] / 10;
+    r + g + b
+}
+
+// Returns the fraction of columns which are as gray as the columns around
+// them. An object on the table, such as a hand, makes the columns it covers
+// stand out.
+fn uniformity(columns: &[u8]) -> f32 {
+    if columns.is_empty() {
+        return 1.0;
+    }
+
+    let half_window = UNIFORMITY_WINDOW / 2;
+    let even = (0..columns.len())
+        .filter(|x| {
+            let from = x.saturating_sub(half_window);
+            let to = (x + half_window).min(columns.len());
+            let mut around = columns[from..to].to_vec();
+            around.sort();
+            let median = around[around.len() / 2];
+            let distance = if columns[*x] > median {
+                columns[*x] - median
+            } else {
+                median - columns[*x]
+            };
+            distance < MINIMUM_BACKGROUND_DISTANCE
+        })
+        .count();
+
+    even as f32 / columns.len() as f32
+}
+
 // Calculates the average distance from the background playfield.
 fn average_distance(frame: &[u8]) -> u8 {
     let total = frame.iter().fold(0usize, |sum, col| sum + *col as usize);
@@ -336,6 +666,75 @@ mod tests {
         assert_eq!(&[148, 091, 111], &b);
     }
 
+    #[test]
+    fn test_uniformity() {
+        // The light falls unevenly, but there's nothing on the table.
+        let empty: Vec<u8> = (0..1280).map(|x| 100 + (x / 20) as u8).collect();
+        assert!((uniformity(&empty) - 1.0).abs() < std::f32::EPSILON);
+        assert!(Calibration::new(&empty, &empty).is_clear());
+
+        // A dark hand covers a tenth of the columns.
+        let mut hand = empty.clone();
+        for col in hand[600..728].iter_mut() {
+            *col = 40;
+        }
+        assert!(uniformity(&hand) < 0.91);
+        assert!(!Calibration::new(&empty, &hand).is_clear());
+    }
+
+    #[test]
+    fn test_zones() {
+        let halves = zones(&[Side::Top, Side::Bottom]);
+        assert_eq!(((0.0, 1200.0), (0.0, 350.0)), halves[0].rectangle());
+        assert_eq!(((0.0, 1200.0), (350.0, 700.0)), halves[1].rectangle());
+
+        let zones = zones(&Side::ALL);
+        assert_eq!(((300.0, 900.0), (350.0, 700.0)), zones[1].rectangle());
+        assert_eq!(((0.0, 300.0), (0.0, 700.0)), zones[2].rectangle());
+        assert_eq!(((900.0, 1200.0), (0.0, 700.0)), zones[3].rectangle());
+        assert_eq!(600, zones[1].position(0.5));
+        assert_eq!(175, zones[2].position(0.25));
+
+        let (back, front) = halves[1].split();
+        assert_eq!(((0.0, 1200.0), (525.0, 700.0)), back.rectangle());
+        assert_eq!(((0.0, 1200.0), (350.0, 525.0)), front.rectangle());
+    }
+
+    #[test]
+    fn test_zone_profile_follows_edge() {
+        // A frame a tenth of the window in size, with a dark spot at given
+        // point of the window.
+        let (width, height) = (120, 70);
+        let frame = |(x, y): (usize, usize)| {
+            let mut frame = vec![200; width * height * 3];
+            // The camera sees the window rotated by 180 degrees.
+            let (col, row) = (width - 1 - x / 10, height - 1 - y / 10);
+            for row in row - 2..=row + 2 {
+                for col in col - 2..=col + 2 {
+                    let i = (row * width + col) * 3;
+                    frame[i..i + 3].copy_from_slice(&[0, 0, 0]);
+                }
+            }
+            frame
+        };
+        let darkest = |profile: Vec<u8>| {
+            let min = *profile.iter().min().unwrap();
+            profile.iter().position(|gray| *gray == min).unwrap()
+        };
+
+        let halves = zones(&[Side::Top, Side::Bottom]);
+        let profile = halves[1].profile(&frame((300, 500)), (width, height));
+        assert_eq!(width, profile.len());
+        assert_eq!(28, darkest(profile));
+        let profile = halves[0].profile(&frame((300, 500)), (width, height));
+        assert!(profile.iter().all(|gray| *gray == profile[0]));
+
+        let left = zones(&Side::ALL)[2];
+        let profile = left.profile(&frame((100, 200)), (width, height));
+        assert_eq!(height, profile.len());
+        assert_eq!(18, darkest(profile));
+    }
+
     #[test]
     fn test_distance_from_background() {
         let averages = &[120, 80, 30];
@@ -0,0 +1,216 @@
+use super::Controller;
+use crate::ball::Ball;
+use crate::paddle::Paddle;
+use crate::{DT, WINDOW_SIZE};
+use rand::rngs::ThreadRng;
+use rand::Rng;
+use std::env;
+
+/// How well does the AI play.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    /// Reads the difficulty of given player from `PONG_AI_<id>` env var,
+    /// which is one of "easy", "medium" (default) or "hard".
+    pub fn from_env(player_id: u8) -> Self {
+        let var = format!("PONG_AI_{}", player_id);
+        match env::var(&var).as_ref().map(String::as_str) {
+            Ok("easy") => Difficulty::Easy,
+            Err(_) | Ok("medium") => Difficulty::Medium,
+            Ok("hard") => Difficulty::Hard,
+            Ok(other) => panic!("Unknown difficulty {} in {}", other, var),
+        }
+    }
+
+    fn skill(self) -> Skill {
+        match self {
+            Difficulty::Easy => Skill {
+                reaction: 20,
+                max_speed: 300.0,
+                aim_error: 70.0,
+            },
+            Difficulty::Medium => Skill {
+                reaction: 10,
+                max_speed: 480.0,
+                aim_error: 35.0,
+            },
+            Difficulty::Hard => Skill {
+                reaction: 4,
+                max_speed: 720.0,
+                aim_error: 10.0,
+            },
+        }
+    }
+}
+
+// Limits which make the AI play like a human.
+#[derive(Clone, Copy, Debug)]
+struct Skill {
+    // How many ticks does it take to notice that the ball changed direction.
+    reaction: u32,
+
+    // How many pixels per second can the paddle move.
+    max_speed: f32,
+
+    // How many pixels at most does the AI miss the point it aims at.
+    aim_error: f32,
+}
+
+/// Predicts where the ball is going to cross the paddle's line, including
+/// the bounces off the walls, and moves the paddle there. It only reacts to
+/// a bounce after a while, its paddle is not faster than a hand and it
+/// doesn't aim perfectly.
+pub struct AiController {
+    skill: Skill,
+
+    // Whether the ball was going right and down when the AI last reacted to
+    // it. A bounce off a paddle or a wall turns one of them around, a spin
+    // which only curves the ball's path usually doesn't.
+    heading: Option<(bool, bool)>,
+
+    // How many ticks until the AI reacts to the ball's new heading.
+    reaction: u32,
+
+    // How many pixels does the AI miss by while the ball comes at it, drawn
+    // once the ball turns towards its paddle.
+    error: f32,
+
+    // Where is the AI moving the paddle.
+    target: f32,
+
+    rng: ThreadRng,
+}
+
+impl AiController {
+    pub fn new(difficulty: Difficulty) -> Self {
+        Self::with_skill(difficulty.skill())
+    }
+
+    fn with_skill(skill: Skill) -> Self {
+        Self {
+            skill,
+            heading: None,
+            reaction: 0,
+            error: 0.0,
+            target: WINDOW_SIZE.0 / 2.0,
+            rng: ThreadRng::default(),
+        }
+    }
+
+    // Decides where to go when the ball is heading in the new direction. If
+    // the ball moves away, goes back to the middle.
+    fn aim(&self, ball: &Ball, paddle: &Paddle) -> f32 {
+        match ball.predict_x(paddle.ball_line(ball.radius)) {
+            Some(x) => x + self.error,
+            None => WINDOW_SIZE.0 / 2.0,
+        }
+    }
+}
+
+impl Controller for AiController {
+    fn target(&mut self, ball: &Ball, paddle: &Paddle) -> Option<f32> {
+        let (x, y) = ball.direction();
+        let heading = (x > 0.0, y > 0.0);
+        if self.heading != Some(heading) {
+            // The ball has bounced, the AI notices after a while.
+            if self.reaction == 0 && self.heading.is_some() {
+                self.reaction = self.skill.reaction;
+            }
+
+            if self.reaction <= 1 {
+                // A bounce off a wall on the way doesn't change how well the
+                // AI aims, only a new approach does.
+                let turned =
+                    self.heading.map(|(_, down)| down) != Some(y > 0.0);
+                if turned && self.skill.aim_error > 0.0 {
+                    let error = self.skill.aim_error;
+                    self.error = self.rng.gen_range(-error, error);
+                }
+                self.reaction = 0;
+                self.heading = Some(heading);
+                self.target = self.aim(ball, paddle);
+            } else {
+                self.reaction -= 1;
+            }
+        }
+
+        let distance = self.target - paddle.x;
+        if distance.abs() < 1.0 {
+            return None;
+        }
+
+        let max_step = self.skill.max_speed * DT;
+        let step = distance.max(-max_step).min(max_step);
+        Some(paddle.x + step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moves_to_predicted_point() {
+        let skill = Skill {
+            reaction: 3,
+            max_speed: 10.0 / DT,
+            aim_error: 0.0,
+        };
+        let mut ai = AiController::with_skill(skill);
+        let mut ball = Ball::default();
+        let mut paddle = Paddle::new(1);
+        let expected = ball.predict_x(paddle.ball_line(ball.radius)).unwrap();
+
+        // The first look at the ball is immediate.
+        let x = paddle.x;
+        let target = ai.target(&ball, &paddle).unwrap();
+        assert!((x - 10.0 - target).abs() < 0.01);
+        for _ in 0..100 {
+            if let Some(x) = ai.target(&ball, &paddle) {
+                paddle.move_to(x);
+            }
+        }
+        assert!((paddle.x - expected).abs() < 1.0);
+
+        // The ball now moves away, it takes three ticks to notice.
+        let (x, y) = ball.velocity();
+        ball.follow(ball.center, (x, -y));
+        assert_eq!(None, ai.target(&ball, &paddle));
+        assert_eq!(None, ai.target(&ball, &paddle));
+        let target = ai.target(&ball, &paddle).unwrap();
+        assert!((paddle.x + 10.0 - target).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_ignores_spin() {
+        let skill = Skill {
+            reaction: 1,
+            max_speed: 10.0 / DT,
+            aim_error: 50.0,
+        };
+        let mut ai = AiController::with_skill(skill);
+        let paddle = Paddle::new(1);
+
+        // The other player swipes the ball, which curves towards the AI.
+        let mut other = Paddle::new(0);
+        other.velocity = 300.0;
+        let mut ball = Ball::default();
+        ball.center.0 = other.x;
+        ball.aim(&other);
+        let (x, _) = ball.direction();
+
+        ai.target(&ball, &paddle);
+        let target = ai.target;
+        for _ in 0..30 {
+            ball.tick();
+            ai.target(&ball, &paddle);
+            assert!((ai.target - target).abs() < 0.01);
+        }
+        assert!((ball.direction().0 - x).abs() > 0.01);
+    }
+}
@@ -0,0 +1,21 @@
+use super::Controller;
+use crate::ball::Ball;
+use crate::paddle::Paddle;
+use std::sync::{Arc, Mutex};
+
+/// Follows the position which the camera thread reads from the playfield.
+pub struct CameraController {
+    position: Arc<Mutex<u32>>,
+}
+
+impl CameraController {
+    pub fn new(position: Arc<Mutex<u32>>) -> Self {
+        Self { position }
+    }
+}
+
+impl Controller for CameraController {
+    fn target(&mut self, _ball: &Ball, _paddle: &Paddle) -> Option<f32> {
+        Some(*self.position.lock().unwrap() as f32)
+    }
+}
@@ -0,0 +1,127 @@
+use super::motion::Motion;
+use super::{Controller, Event};
+use crate::ball::Ball;
+use crate::paddle::Paddle;
+use ggez::event::{Axis, Button};
+use std::mem;
+
+/// Sticks rarely rest exactly in the middle. Tilt smaller than this is
+/// ignored.
+const DEAD_ZONE: f32 = 0.15;
+
+/// Moves the paddle by tilting the left stick or with the d-pad of the
+/// gamepad and serves with the bottom face button. The first gamepad which
+/// was used belongs to the first player and so on.
+pub struct GamepadController {
+    gamepad: usize,
+
+    // How much is the stick tilted, in interval <-1; 1>.
+    tilt: f32,
+
+    left_pressed: bool,
+
+    right_pressed: bool,
+
+    serve_pressed: bool,
+
+    motion: Motion,
+}
+
+impl GamepadController {
+    pub fn new(player_id: u8, motion: Motion) -> Self {
+        Self {
+            gamepad: player_id as usize,
+            tilt: 0.0,
+            left_pressed: false,
+            right_pressed: false,
+            serve_pressed: false,
+            motion,
+        }
+    }
+
+    fn press(&mut self, button: Button, pressed: bool) {
+        match button {
+            Button::DPadLeft => self.left_pressed = pressed,
+            Button::DPadRight => self.right_pressed = pressed,
+            Button::South if pressed => self.serve_pressed = true,
+            _ => (),
+        }
+    }
+
+    // How fast should the paddle go. The d-pad wins over the stick.
+    fn throttle(&self) -> f32 {
+        match (self.left_pressed, self.right_pressed) {
+            (true, false) => -1.0,
+            (false, true) => 1.0,
+            _ if self.tilt.abs() < DEAD_ZONE => 0.0,
+            // Rescales the tilt so that the paddle starts slowly just
+            // outside of the dead zone.
+            _ => {
+                (self.tilt - DEAD_ZONE * self.tilt.signum()) / (1.0 - DEAD_ZONE)
+            }
+        }
+    }
+}
+
+impl Controller for GamepadController {
+    fn target(&mut self, _ball: &Ball, paddle: &Paddle) -> Option<f32> {
+        match self.motion.towards(self.throttle()) {
+            step if step == 0.0 => None,
+            step => Some(paddle.x + step),
+        }
+    }
+
+    fn handle(&mut self, event: &Event) {
+        match *event {
+            Event::GamepadAxis {
+                gamepad,
+                axis: Axis::LeftStickX,
+                value,
+            } if gamepad == self.gamepad => self.tilt = value,
+            Event::GamepadButtonDown { gamepad, button }
+                if gamepad == self.gamepad =>
+            {
+                self.press(button, true)
+            }
+            Event::GamepadButtonUp { gamepad, button }
+                if gamepad == self.gamepad =>
+            {
+                self.press(button, false)
+            }
+            _ => (),
+        }
+    }
+
+    fn serves(&mut self) -> bool {
+        mem::replace(&mut self.serve_pressed, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle() {
+        let mut controller = GamepadController::new(0, Motion::new(1.0, 1.0));
+        let tilt = |controller: &mut GamepadController, value| {
+            controller.handle(&Event::GamepadAxis {
+                gamepad: 0,
+                axis: Axis::LeftStickX,
+                value,
+            });
+            controller.throttle()
+        };
+
+        assert!(tilt(&mut controller, 0.1).abs() < 0.001);
+        assert!((tilt(&mut controller, 1.0) - 1.0).abs() < 0.001);
+        assert!((tilt(&mut controller, -1.0) + 1.0).abs() < 0.001);
+        assert!((tilt(&mut controller, 0.575) - 0.5).abs() < 0.001);
+
+        controller.handle(&Event::GamepadButtonDown {
+            gamepad: 0,
+            button: Button::DPadRight,
+        });
+        assert!((controller.throttle() - 1.0).abs() < 0.001);
+    }
+}
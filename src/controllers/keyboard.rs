@@ -0,0 +1,230 @@
+use super::motion::Motion;
+use super::{Controller, Event};
+use crate::ball::Ball;
+use crate::paddle::Paddle;
+use ggez::event::KeyCode;
+use std::env;
+use std::mem;
+
+/// Moves the paddle while a key is held and serves with another key. By
+/// default the first player uses the arrows, the second one uses A, D and W,
+/// the third one J, L and I and the fourth one 4, 6 and 8 on the numpad.
+pub struct KeyboardController {
+    left: KeyCode,
+    right: KeyCode,
+    serve: KeyCode,
+    left_pressed: bool,
+    right_pressed: bool,
+    serve_pressed: bool,
+    motion: Motion,
+}
+
+impl KeyboardController {
+    pub fn new(player_id: u8, motion: Motion) -> Self {
+        let (left, right, serve) = keys_from_env(player_id);
+
+        Self {
+            left,
+            right,
+            serve,
+            left_pressed: false,
+            right_pressed: false,
+            serve_pressed: false,
+            motion,
+        }
+    }
+}
+
+impl Controller for KeyboardController {
+    fn target(&mut self, _ball: &Ball, paddle: &Paddle) -> Option<f32> {
+        let throttle = match (self.left_pressed, self.right_pressed) {
+            (true, false) => -1.0,
+            (false, true) => 1.0,
+            _ => 0.0,
+        };
+
+        match self.motion.towards(throttle) {
+            step if step == 0.0 => None,
+            step => Some(paddle.x + step),
+        }
+    }
+
+    fn handle(&mut self, event: &Event) {
+        match *event {
+            Event::KeyDown(key) if key == self.left => self.left_pressed = true,
+            Event::KeyUp(key) if key == self.left => self.left_pressed = false,
+            Event::KeyDown(key) if key == self.right => {
+                self.right_pressed = true
+            }
+            Event::KeyUp(key) if key == self.right => {
+                self.right_pressed = false
+            }
+            Event::KeyDown(key) if key == self.serve => {
+                self.serve_pressed = true
+            }
+            _ => (),
+        }
+    }
+
+    fn serves(&mut self) -> bool {
+        mem::replace(&mut self.serve_pressed, false)
+    }
+}
+
+// Reads the keys of given player from `PONG_KEYS_<id>` env var, e.g.
+// `PONG_KEYS_1=J,L,I` moves the second player to the left with J and to the
+// right with L and serves with I. Without the third key, the player serves
+// with their default serve key.
+fn keys_from_env(player_id: u8) -> (KeyCode, KeyCode, KeyCode) {
+    let default = default_keys(player_id);
+    let var = format!("PONG_KEYS_{}", player_id);
+    match env::var(&var) {
+        Ok(keys) => parse_keys(&keys, default)
+            .unwrap_or_else(|| panic!("Invalid {}: {}", var, keys)),
+        Err(_) => default,
+    }
+}
+
+// Returns the keys which move the paddle of given player to the left and to
+// the right and which serve, unless they are set otherwise.
+fn default_keys(player_id: u8) -> (KeyCode, KeyCode, KeyCode) {
+    match player_id {
+        0 => (KeyCode::Left, KeyCode::Right, KeyCode::Up),
+        1 => (KeyCode::A, KeyCode::D, KeyCode::W),
+        2 => (KeyCode::J, KeyCode::L, KeyCode::I),
+        _ => (KeyCode::Numpad4, KeyCode::Numpad6, KeyCode::Numpad8),
+    }
+}
+
+// Reads two or three comma separated keys as in `keys_from_env`. The serve
+// key is taken from the default keys if there are only two.
+fn parse_keys(
+    text: &str,
+    (_, _, default_serve): (KeyCode, KeyCode, KeyCode),
+) -> Option<(KeyCode, KeyCode, KeyCode)> {
+    let keys: Option<Vec<_>> =
+        text.split(',').map(|key| parse_key(key.trim())).collect();
+    match *keys?.as_slice() {
+        [left, right] => Some((left, right, default_serve)),
+        [left, right, serve] => Some((left, right, serve)),
+        _ => None,
+    }
+}
+
+// Reads the name of a key, which is the name of the key code variant.
+fn parse_key(name: &str) -> Option<KeyCode> {
+    use KeyCode::*;
+    let key = match name {
+        "A" => A,
+        "B" => B,
+        "C" => C,
+        "D" => D,
+        "E" => E,
+        "F" => F,
+        "G" => G,
+        "H" => H,
+        "I" => I,
+        "J" => J,
+        "K" => K,
+        "L" => L,
+        "M" => M,
+        "N" => N,
+        "O" => O,
+        "P" => P,
+        "Q" => Q,
+        "R" => R,
+        "S" => S,
+        "T" => T,
+        "U" => U,
+        "V" => V,
+        "W" => W,
+        "X" => X,
+        "Y" => Y,
+        "Z" => Z,
+        "Key1" => Key1,
+        "Key2" => Key2,
+        "Key3" => Key3,
+        "Key4" => Key4,
+        "Key5" => Key5,
+        "Key6" => Key6,
+        "Key7" => Key7,
+        "Key8" => Key8,
+        "Key9" => Key9,
+        "Key0" => Key0,
+        "Left" => Left,
+        "Right" => Right,
+        "Up" => Up,
+        "Down" => Down,
+        "Numpad4" => Numpad4,
+        "Numpad6" => Numpad6,
+        "Numpad8" => Numpad8,
+        "Comma" => Comma,
+        "Period" => Period,
+        "Space" => Space,
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DT;
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(Some(KeyCode::J), parse_key("J"));
+        assert_eq!(Some(KeyCode::Left), parse_key("Left"));
+        assert_eq!(None, parse_key("left"));
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        let default = default_keys(1);
+        assert_eq!(
+            Some((KeyCode::J, KeyCode::L, KeyCode::W)),
+            parse_keys("J,L", default)
+        );
+        assert_eq!(
+            Some((KeyCode::J, KeyCode::L, KeyCode::I)),
+            parse_keys("J, L, I", default)
+        );
+        assert_eq!(None, parse_keys("J", default));
+        assert_eq!(None, parse_keys("J,L,I,K", default));
+        assert_eq!(None, parse_keys("J,left", default));
+    }
+
+    #[test]
+    fn test_holding_key_speeds_up() {
+        // Gains 60 px/s each tick, that is a pixel per tick, up to two
+        // pixels per tick.
+        let mut controller =
+            KeyboardController::new(0, Motion::new(60.0 / DT, 120.0));
+        let ball = Ball::default();
+        let mut paddle = Paddle::new(0);
+        let x = paddle.x;
+
+        assert_eq!(None, controller.target(&ball, &paddle));
+        controller.handle(&Event::KeyDown(KeyCode::Right));
+        for expected in [x + 1.0, x + 3.0, x + 5.0].iter() {
+            let target = controller.target(&ball, &paddle).unwrap();
+            assert!((expected - target).abs() < 0.01);
+            paddle.move_to(target);
+        }
+
+        controller.handle(&Event::KeyUp(KeyCode::Right));
+        let target = controller.target(&ball, &paddle).unwrap();
+        assert!((x + 6.0 - target).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_serve_key() {
+        let mut controller =
+            KeyboardController::new(0, Motion::new(60.0 / DT, 120.0));
+        assert!(!controller.serves());
+        controller.handle(&Event::KeyDown(KeyCode::Up));
+        assert!(controller.serves());
+        assert!(!controller.serves());
+    }
+}
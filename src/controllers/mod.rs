@@ -0,0 +1,148 @@
+mod ai;
+mod camera;
+mod gamepad;
+mod keyboard;
+mod motion;
+mod mouse;
+mod network;
+use crate::ball::Ball;
+use crate::paddle::{Paddle, Side};
+use ggez::event::{Axis, Button, KeyCode, MouseButton};
+use motion::Motion;
+use std::env;
+use std::sync::{Arc, Mutex};
+
+/// Anything that can move a paddle. Each player has their own controller
+/// which can be swapped during the game.
+pub trait Controller {
+    /// Where should the paddle go on its axis. Returns `None` if the
+    /// controller has nothing new to say, e.g. no key is pressed, and the
+    /// paddle stays where it is.
+    fn target(&mut self, ball: &Ball, paddle: &Paddle) -> Option<f32>;
+
+    /// Lets the controller react to user input. Most controllers are only
+    /// interested in some events, hence the default ignores them all.
+    fn handle(&mut self, _event: &Event) {
+        //
+    }
+
+    /// Whether the player has asked for the ball to be served since the last
+    /// time this was called.
+    fn serves(&mut self) -> bool {
+        false
+    }
+}
+
+/// User input forwarded from the game window to the controllers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    MouseMotion {
+        x: f32,
+    },
+    MouseButtonDown(MouseButton),
+    KeyDown(KeyCode),
+    KeyUp(KeyCode),
+    /// Gamepads are numbered in the order they were first used.
+    GamepadAxis {
+        gamepad: usize,
+        axis: Axis,
+        value: f32,
+    },
+    GamepadButtonDown {
+        gamepad: usize,
+        button: Button,
+    },
+    GamepadButtonUp {
+        gamepad: usize,
+        button: Button,
+    },
+}
+
+/// Which kind of controller moves a paddle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Camera,
+    Mouse,
+    Keyboard,
+    Gamepad,
+    Ai,
+    Network,
+}
+
+impl Kind {
+    /// All kinds in the order in which they are cycled through during the
+    /// game.
+    pub const ALL: [Kind; 6] = [
+        Kind::Camera,
+        Kind::Mouse,
+        Kind::Keyboard,
+        Kind::Gamepad,
+        Kind::Ai,
+        Kind::Network,
+    ];
+
+    /// Reads the kind of controller of given player from `PONG_PLAYER_<id>`
+    /// env var, e.g. `PONG_PLAYER_0=keyboard`. By default the first player
+    /// plays with the mouse, the second one with the camera and the others
+    /// with the keyboard.
+    pub fn from_env(player_id: u8) -> Self {
+        let var = format!("PONG_PLAYER_{}", player_id);
+        match env::var(&var).as_ref().map(String::as_str) {
+            Ok("camera") => Kind::Camera,
+            Ok("mouse") => Kind::Mouse,
+            Ok("keyboard") => Kind::Keyboard,
+            Ok("gamepad") => Kind::Gamepad,
+            Ok("ai") => Kind::Ai,
+            Ok("network") => Kind::Network,
+            Ok(other) => panic!("Unknown controller {} in {}", other, var),
+            Err(_) if player_id == 0 => Kind::Mouse,
+            Err(_) if player_id == 1 => Kind::Camera,
+            Err(_) => Kind::Keyboard,
+        }
+    }
+
+    /// The kind which follows this one in `ALL`.
+    pub fn next(self) -> Self {
+        let i = Kind::ALL.iter().position(|kind| *kind == self).unwrap();
+        Kind::ALL[(i + 1) % Kind::ALL.len()]
+    }
+}
+
+/// Creates a controller of given kind for given player, who plays along
+/// given edge. Camera controllers need the positions which the camera
+/// updates. Returns `None` if the controller cannot be created, e.g. because
+/// there's no camera.
+pub fn new(
+    kind: Kind,
+    player_id: u8,
+    side: Side,
+    camera_positions: Option<&[Arc<Mutex<u32>>]>,
+) -> Option<Box<dyn Controller>> {
+    let controller: Box<dyn Controller> = match kind {
+        Kind::Camera => {
+            let position = camera_positions?.get(player_id as usize)?;
+            Box::new(camera::CameraController::new(position.clone()))
+        }
+        Kind::Mouse => Box::new(mouse::MouseController::new(side)),
+        Kind::Keyboard => Box::new(keyboard::KeyboardController::new(
+            player_id,
+            Motion::from_env(),
+        )),
+        Kind::Gamepad => Box::new(gamepad::GamepadController::new(
+            player_id,
+            Motion::from_env(),
+        )),
+        Kind::Ai => {
+            Box::new(ai::AiController::new(ai::Difficulty::from_env(player_id)))
+        }
+        Kind::Network => match network::NetworkController::bind(player_id) {
+            Ok(controller) => Box::new(controller),
+            Err(e) => {
+                error!("Cannot listen for player {}: {}", player_id, e);
+                return None;
+            }
+        },
+    };
+
+    Some(controller)
+}
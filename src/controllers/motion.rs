@@ -0,0 +1,85 @@
+use crate::DT;
+use std::env;
+
+/// How many pixels per second does the paddle gain in speed each second.
+const DEFAULT_ACCELERATION: f32 = 5400.0;
+
+/// How many pixels per second can the paddle move at most.
+const DEFAULT_MAX_SPEED: f32 = 720.0;
+
+/// Smooths digital and analogue input into a motion of the paddle. Instead of
+/// jumping from zero to full speed when a key is pressed, the paddle speeds
+/// up and slows down with limited acceleration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Motion {
+    /// Current speed of the paddle in pixels per second. Positive velocity
+    /// moves the paddle to the right.
+    pub velocity: f32,
+
+    acceleration: f32,
+
+    max_speed: f32,
+}
+
+impl Motion {
+    pub fn new(acceleration: f32, max_speed: f32) -> Self {
+        Self {
+            velocity: 0.0,
+            acceleration,
+            max_speed,
+        }
+    }
+
+    /// Reads the acceleration and the max speed from `PONG_ACCELERATION` and
+    /// `PONG_MAX_SPEED` env vars, in pixels per second squared and pixels
+    /// per second.
+    pub fn from_env() -> Self {
+        let read = |var: &str, default: f32| {
+            env::var(var)
+                .map(|value| value.parse().expect("Invalid paddle motion"))
+                .unwrap_or(default)
+        };
+
+        Self::new(
+            read("PONG_ACCELERATION", DEFAULT_ACCELERATION),
+            read("PONG_MAX_SPEED", DEFAULT_MAX_SPEED),
+        )
+    }
+
+    /// Changes the velocity during one tick towards given fraction of the max
+    /// speed, where the throttle is in interval <-1; 1>. Returns how many
+    /// pixels does the paddle move in this tick.
+    pub fn towards(&mut self, throttle: f32) -> f32 {
+        let target = throttle.max(-1.0).min(1.0) * self.max_speed;
+        let max_change = self.acceleration * DT;
+        let change = (target - self.velocity).max(-max_change).min(max_change);
+        self.velocity += change;
+        self.velocity * DT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speeds_up_and_slows_down() {
+        // Gains 120 px/s each tick up to 300 px/s.
+        let mut motion = Motion::new(120.0 / DT, 300.0);
+        let ticks = [
+            (1.0, 120.0),
+            (1.0, 240.0),
+            (1.0, 300.0),
+            (3.0, 300.0),
+            (0.0, 180.0),
+            (0.0, 60.0),
+            (0.0, 0.0),
+            (-0.2, -60.0),
+        ];
+        for (throttle, expected) in ticks.iter() {
+            let step = motion.towards(*throttle);
+            assert!((motion.velocity - expected).abs() < 0.01);
+            assert!((step - expected * DT).abs() < 0.01);
+        }
+    }
+}
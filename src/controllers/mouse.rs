@@ -0,0 +1,68 @@
+use super::{Controller, Event};
+use crate::ball::Ball;
+use crate::paddle::{Paddle, Side};
+use crate::WINDOW_SIZE;
+use ggez::event::MouseButton;
+use std::mem;
+
+/// Follows the mouse cursor on the x axis. A click serves the ball. The
+/// width of the window stretches onto the edge the paddle moves along, which
+/// is shorter for the left and the right edge.
+pub struct MouseController {
+    // Where was the cursor last seen, if it has moved since the game started.
+    x: Option<f32>,
+
+    clicked: bool,
+
+    // How long is the edge the paddle moves along.
+    length: f32,
+}
+
+impl MouseController {
+    pub fn new(side: Side) -> Self {
+        Self {
+            x: None,
+            clicked: false,
+            length: side.length(),
+        }
+    }
+}
+
+impl Controller for MouseController {
+    fn target(&mut self, _ball: &Ball, _paddle: &Paddle) -> Option<f32> {
+        self.x.map(|x| x / WINDOW_SIZE.0 * self.length)
+    }
+
+    fn handle(&mut self, event: &Event) {
+        match *event {
+            Event::MouseMotion { x } => self.x = Some(x),
+            Event::MouseButtonDown(MouseButton::Left) => self.clicked = true,
+            _ => (),
+        }
+    }
+
+    fn serves(&mut self) -> bool {
+        mem::replace(&mut self.clicked, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stretches_window_onto_edge() {
+        let (ball, paddle) = (Ball::default(), Paddle::new(0));
+        let mut top = MouseController::new(Side::Top);
+        let mut left = MouseController::new(Side::Left);
+        assert_eq!(None, left.target(&ball, &paddle));
+
+        let x = WINDOW_SIZE.0 * 0.75;
+        top.handle(&Event::MouseMotion { x });
+        left.handle(&Event::MouseMotion { x });
+        let top = top.target(&ball, &paddle).unwrap();
+        let left = left.target(&ball, &paddle).unwrap();
+        assert!((top - x).abs() < 0.01);
+        assert!((left - WINDOW_SIZE.1 * 0.75).abs() < 0.01);
+    }
+}
@@ -0,0 +1,82 @@
+use super::Controller;
+use crate::ball::Ball;
+use crate::paddle::Paddle;
+use std::io;
+use std::net::UdpSocket;
+
+/// Port on which the first player's controller listens. Each next player
+/// listens on the next port.
+const BASE_PORT: u16 = 9000;
+
+/// Listens for UDP datagrams with the paddle's x coordinate as text, e.g.
+/// "512.5". Malformed datagrams are ignored.
+pub struct NetworkController {
+    socket: UdpSocket,
+}
+
+impl NetworkController {
+    /// Listens on the port given by the player's id on all interfaces.
+    pub fn bind(player_id: u8) -> io::Result<Self> {
+        let port = BASE_PORT + u16::from(player_id);
+        info!("Listening for player {} on UDP port {}", player_id, port);
+        Self::new(UdpSocket::bind(("0.0.0.0", port))?)
+    }
+
+    fn new(socket: UdpSocket) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket })
+    }
+}
+
+impl Controller for NetworkController {
+    fn target(&mut self, _ball: &Ball, _paddle: &Paddle) -> Option<f32> {
+        // Drains all datagrams which arrived since the last tick and keeps
+        // the latest position.
+        let mut buffer = [0; 64];
+        let mut x = None;
+        loop {
+            match self.socket.recv(&mut buffer) {
+                Ok(len) => {
+                    let position = String::from_utf8_lossy(&buffer[..len])
+                        .trim()
+                        .parse()
+                        .ok();
+                    x = position.or(x);
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    warn!("Cannot receive position: {}", e);
+                    break;
+                }
+            }
+        }
+
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_latest_position() {
+        let mut controller =
+            NetworkController::new(UdpSocket::bind("127.0.0.1:0").unwrap())
+                .unwrap();
+        let address = controller.socket.local_addr().unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let ball = Ball::default();
+        let paddle = Paddle::new(0);
+
+        assert_eq!(None, controller.target(&ball, &paddle));
+
+        client.send_to(b"100.5", address).unwrap();
+        client.send_to(b"300\n", address).unwrap();
+        client.send_to(b"garbage", address).unwrap();
+        // Lets the datagrams arrive.
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert_eq!(Some(300.0), controller.target(&ball, &paddle));
+        assert_eq!(None, controller.target(&ball, &paddle));
+    }
+}
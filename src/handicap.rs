@@ -0,0 +1,191 @@
+use super::paddle::PADDLE_SIZE;
+use std::env;
+use std::fmt;
+
+/// How much easier, or harder, is the match for a player, so that adults
+/// and children can play together.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Handicap {
+    /// How wide is the player's paddle.
+    pub paddle_width: f32,
+
+    /// How many times as fast as usual do the balls go while they head
+    /// towards the player.
+    pub ball_speed: f32,
+
+    /// Whether the balls heading towards the player keep their speed instead
+    /// of speeding up as the rally goes on and as bumpers kick them.
+    pub steady: bool,
+
+    /// How many points ahead does the player start each set, or how many
+    /// more lives do they have.
+    pub head_start: u32,
+}
+
+/// No handicap at all.
+pub const NONE: Handicap = Handicap {
+    paddle_width: PADDLE_SIZE.0,
+    ball_speed: 1.0,
+    steady: false,
+    head_start: 0,
+};
+
+/// A slower ball which doesn't speed up and a bigger paddle.
+pub const KIDS: Handicap = Handicap {
+    paddle_width: PADDLE_SIZE.0 * 1.6,
+    ball_speed: 0.7,
+    steady: true,
+    head_start: 0,
+};
+
+/// The presets in the order in which the operator cycles through them.
+pub const PRESETS: [Handicap; 2] = [NONE, KIDS];
+
+impl Default for Handicap {
+    fn default() -> Self {
+        NONE
+    }
+}
+
+impl Handicap {
+    /// Reads the handicap of given player from `PONG_HANDICAP_<id>` env var,
+    /// which is either a preset, "none" (default) or "kids", or a comma
+    /// separated list of changes to no handicap, e.g.
+    /// `PONG_HANDICAP_1=width=150,speed=0.8,steady,points=2`.
+    pub fn from_env(player_id: u8) -> Self {
+        let var = format!("PONG_HANDICAP_{}", player_id);
+        match env::var(&var) {
+            Ok(value) => Self::parse(&value)
+                .unwrap_or_else(|| panic!("Invalid {}: {}", var, value)),
+            Err(_) => NONE,
+        }
+    }
+
+    /// Reads a handicap written as in `from_env`. The width of the paddle
+    /// and the speed of the balls must be positive numbers.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim() {
+            "none" => return Some(NONE),
+            "kids" => return Some(KIDS),
+            _ => (),
+        }
+
+        let positive = |value: &str| {
+            value
+                .parse()
+                .ok()
+                .filter(|x: &f32| x.is_finite() && *x > 0.0)
+        };
+        let mut handicap = NONE;
+        for change in text.split(',') {
+            let mut parts = change.trim().splitn(2, '=');
+            match (parts.next()?, parts.next()) {
+                ("width", Some(width)) => {
+                    handicap.paddle_width = positive(width)?
+                }
+                ("speed", Some(speed)) => {
+                    handicap.ball_speed = positive(speed)?
+                }
+                ("points", Some(points)) => {
+                    handicap.head_start = points.parse().ok()?
+                }
+                ("steady", None) => handicap.steady = true,
+                _ => return None,
+            }
+        }
+
+        Some(handicap)
+    }
+
+    /// Returns the preset which follows this handicap. A handicap which
+    /// isn't a preset is followed by the first one.
+    pub fn next(self) -> Self {
+        match PRESETS.iter().position(|preset| *preset == self) {
+            Some(i) => PRESETS[(i + 1) % PRESETS.len()],
+            None => PRESETS[0],
+        }
+    }
+
+    /// Returns how the handicap is shown on the scoreboard, which is empty
+    /// if there's none.
+    pub fn label(self) -> String {
+        if self == KIDS {
+            return "KIDS".to_string();
+        }
+
+        let mut parts = Vec::new();
+        if (self.paddle_width - PADDLE_SIZE.0).abs() > std::f32::EPSILON {
+            parts.push(format!("paddle {}", self.paddle_width));
+        }
+        if (self.ball_speed - 1.0).abs() > std::f32::EPSILON {
+            parts.push(format!("ball {}%", (self.ball_speed * 100.0).round()));
+        }
+        if self.steady {
+            parts.push("steady".to_string());
+        }
+        if self.head_start > 0 {
+            parts.push(format!("+{}", self.head_start));
+        }
+        parts.join(" ")
+    }
+}
+
+impl fmt::Display for Handicap {
+    /// Writes the handicap as a list of changes which `parse` reads back.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "width={},speed={},points={}",
+            self.paddle_width, self.ball_speed, self.head_start
+        )?;
+        if self.steady {
+            write!(f, ",steady")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Some(KIDS), Handicap::parse("kids"));
+        assert_eq!(Some(NONE), Handicap::parse(" none"));
+        let handicap = Handicap::parse("width=150, speed=0.8,steady,points=2");
+        assert_eq!(
+            Some(Handicap {
+                paddle_width: 150.0,
+                ball_speed: 0.8,
+                steady: true,
+                head_start: 2,
+            }),
+            handicap
+        );
+        assert_eq!("paddle 150 ball 80% steady +2", handicap.unwrap().label());
+        let written = handicap.unwrap().to_string();
+        assert_eq!(handicap, Handicap::parse(&written));
+
+        assert_eq!(None, Handicap::parse("width"));
+        assert_eq!(None, Handicap::parse("speed=fast"));
+        assert_eq!(None, Handicap::parse("tall=3"));
+
+        // The ball would stop and the paddle would be gone.
+        assert_eq!(None, Handicap::parse("width=0"));
+        assert_eq!(None, Handicap::parse("width=-50"));
+        assert_eq!(None, Handicap::parse("width=inf"));
+        assert_eq!(None, Handicap::parse("speed=0"));
+        assert_eq!(None, Handicap::parse("speed=-1"));
+        assert_eq!(None, Handicap::parse("speed=NaN"));
+    }
+
+    #[test]
+    fn test_cycle_presets() {
+        assert_eq!(KIDS, NONE.next());
+        assert_eq!(NONE, KIDS.next());
+        assert_eq!(NONE, Handicap::parse("points=1").unwrap().next());
+        assert_eq!("", NONE.label());
+        assert_eq!("KIDS", KIDS.label());
+    }
+}
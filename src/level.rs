@@ -0,0 +1,264 @@
+use super::paddle::Track;
+use super::{DT, WINDOW_SIZE};
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Mesh, Rect, BLACK};
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+use serde::Deserialize;
+use std::f32::consts::PI;
+use std::path::Path;
+use std::{env, fs, io};
+
+/// What the obstacles are filled with.
+const OBSTACLE_COLOR: Color = Color::new(0.6, 0.6, 0.6, 1.0);
+
+/// How thick is the outline of the bumpers.
+const BUMPER_OUTLINE: f32 = 4.0;
+
+/// How many seconds does a moving obstacle take to get to where it moves and
+/// back, unless the level says otherwise.
+const DEFAULT_PERIOD: f32 = 4.0;
+
+/// The layout of the court. Levels are written in TOML, e.g.
+///
+/// ```toml
+/// spawns = [[600, 400]]
+///
+/// [[tracks]]
+/// offset = 40
+///
+/// [[tracks]]
+/// offset = 40
+///
+/// [[obstacles]]
+/// shape = "circle"
+/// radius = 40
+/// center = [300, 400]
+/// bumper = 120
+///
+/// [[obstacles]]
+/// shape = "rectangle"
+/// width = 200
+/// height = 20
+/// center = [600, 300]
+/// moves_to = [600, 500]
+/// period = 6
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Level {
+    /// What the balls bounce off besides the walls and the paddles.
+    pub obstacles: Vec<Obstacle>,
+
+    /// Where do balls wait for a serve, one of them is picked at random. The
+    /// middle of the window if there are none.
+    pub spawns: Vec<(f32, f32)>,
+
+    /// Where does each player's paddle move, in the order of their ids.
+    /// Players without a track move along their whole edge.
+    pub tracks: Vec<Track>,
+}
+
+/// Something on the court the balls bounce off.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Obstacle {
+    #[serde(flatten)]
+    pub shape: Shape,
+
+    pub center: (f32, f32),
+
+    /// Where does the obstacle move to and back from its center, if at all.
+    #[serde(default)]
+    pub moves_to: Option<(f32, f32)>,
+
+    /// How many seconds does the obstacle take to get to where it moves and
+    /// back.
+    #[serde(default = "default_period")]
+    pub period: f32,
+
+    /// How many pixels per second does a ball speed up when it bounces off
+    /// the obstacle. The kick wears off as a swipe of a paddle does.
+    #[serde(default)]
+    pub bumper: f32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(tag = "shape", rename_all = "lowercase")]
+pub enum Shape {
+    Rectangle { width: f32, height: f32 },
+    Circle { radius: f32 },
+}
+
+impl Level {
+    /// Loads the level from the file given by `PONG_LEVEL` env var. The
+    /// court is empty by default.
+    pub fn from_env() -> Self {
+        match env::var("PONG_LEVEL") {
+            Ok(path) => Self::load(&path).unwrap_or_else(|e| {
+                panic!("Cannot load level {}: {}", path, e)
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Loads a level file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Reads a level written in TOML.
+    pub fn parse(text: &str) -> io::Result<Self> {
+        toml::from_str(text)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Returns where a ball waits for a serve given a random number.
+    pub fn spawn(&self, random: usize) -> (f32, f32) {
+        if self.spawns.is_empty() {
+            (WINDOW_SIZE.0 / 2.0, WINDOW_SIZE.1 / 2.0)
+        } else {
+            self.spawns[random % self.spawns.len()]
+        }
+    }
+}
+
+impl Obstacle {
+    /// Returns where the obstacle is after given number of ticks of play.
+    /// A moving obstacle slows down towards either end of its way.
+    pub fn center_at(&self, ticks: u32) -> (f32, f32) {
+        let (x, y) = match self.moves_to {
+            Some(to) if self.period > 0.0 => to,
+            _ => return self.center,
+        };
+
+        let angle = ticks as f32 * DT / self.period * 2.0 * PI;
+        let along = (1.0 - angle.cos()) / 2.0;
+        (
+            self.center.0 + (x - self.center.0) * along,
+            self.center.1 + (y - self.center.1) * along,
+        )
+    }
+
+    /// Draws the obstacle with given center. Bumpers are outlined.
+    pub fn draw(
+        &self,
+        ctx: &mut Context,
+        (x, y): (f32, f32),
+    ) -> GameResult<()> {
+        let modes = if self.bumper > 0.0 {
+            vec![
+                (DrawMode::fill(), OBSTACLE_COLOR),
+                (DrawMode::stroke(BUMPER_OUTLINE), BLACK),
+            ]
+        } else {
+            vec![(DrawMode::fill(), OBSTACLE_COLOR)]
+        };
+
+        for (mode, color) in modes {
+            let mesh = match self.shape {
+                Shape::Rectangle { width, height } => Mesh::new_rectangle(
+                    ctx,
+                    mode,
+                    Rect::new(x - width / 2.0, y - height / 2.0, width, height),
+                    color,
+                )?,
+                Shape::Circle { radius } => Mesh::new_circle(
+                    ctx,
+                    mode,
+                    Point2::new(x, y),
+                    radius,
+                    0.5,
+                    color,
+                )?,
+            };
+            graphics::draw(ctx, &mesh, DrawParam::default())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn default_period() -> f32 {
+    DEFAULT_PERIOD
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TICKS_PER_SECOND;
+
+    #[test]
+    fn test_parse() {
+        let level = Level::parse(
+            r#"
+            spawns = [[600, 400], [600.5, 300]]
+
+            [[tracks]]
+            offset = 40
+            from = 100
+
+            [[tracks]]
+
+            [[obstacles]]
+            shape = "circle"
+            radius = 40
+            center = [300, 400]
+            bumper = 120
+
+            [[obstacles]]
+            shape = "rectangle"
+            width = 200
+            height = 20
+            center = [600, 300]
+            moves_to = [600, 500]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(vec![(600.0, 400.0), (600.5, 300.0)], level.spawns);
+        assert_eq!(
+            Track {
+                offset: 40.0,
+                from: 100.0,
+                ..Default::default()
+            },
+            level.tracks[0]
+        );
+        assert_eq!(Track::default(), level.tracks[1]);
+        assert_eq!(Shape::Circle { radius: 40.0 }, level.obstacles[0].shape);
+        assert_eq!(None, level.obstacles[0].moves_to);
+        assert_eq!(Some((600.0, 500.0)), level.obstacles[1].moves_to);
+
+        assert_eq!(Level::default(), Level::parse("").unwrap());
+        assert!(Level::parse("[[obstacles]]\nshape = \"star\"").is_err());
+        assert_eq!(
+            3,
+            Level::load("levels/bumpers.toml").unwrap().obstacles.len()
+        );
+    }
+
+    #[test]
+    fn test_moving_obstacle() {
+        let obstacle = Obstacle {
+            shape: Shape::Circle { radius: 10.0 },
+            center: (100.0, 100.0),
+            moves_to: Some((100.0, 300.0)),
+            period: 2.0,
+            bumper: 0.0,
+        };
+        let distance = |(x, y): (f32, f32), (a, b): (f32, f32)| {
+            (x - a).abs() + (y - b).abs()
+        };
+
+        assert!(distance((100.0, 100.0), obstacle.center_at(0)) < 0.01);
+        let there = obstacle.center_at(TICKS_PER_SECOND);
+        assert!(distance((100.0, 300.0), there) < 0.01);
+        let back = obstacle.center_at(2 * TICKS_PER_SECOND);
+        assert!(distance((100.0, 100.0), back) < 0.01);
+
+        let still = Obstacle {
+            moves_to: None,
+            ..obstacle
+        };
+        assert_eq!((100.0, 100.0), still.center_at(30));
+    }
+}
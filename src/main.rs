@@ -3,9 +3,23 @@ extern crate log;
 
 mod ball;
 mod camera;
+mod controllers;
+mod handicap;
+mod level;
+mod markers;
+mod multiball;
 mod paddle;
 mod phases;
+mod physics;
 mod pong;
+mod powerups;
+mod rally;
+mod robot;
+mod rules;
+mod scoreboard;
+mod serve;
+mod simulation;
+mod solo;
 
 use ggez::event;
 use ggez::ContextBuilder;
@@ -14,6 +28,13 @@ use pong::Pong;
 /// How large should the game window be in pixels.
 const WINDOW_SIZE: (f32, f32) = (1200.0, 700.0);
 
+/// How many times per second does the game move the ball and the paddles.
+/// The game runs at the same speed regardless of the frame rate.
+const TICKS_PER_SECOND: u32 = 60;
+
+/// How many seconds does one tick of the game take.
+const DT: f32 = 1.0 / TICKS_PER_SECOND as f32;
+
 fn main() {
     dotenv::dotenv().ok();
     env_logger::init();
@@ -31,4 +52,6 @@ fn main() {
         Ok(_) => info!("Good game."),
         Err(e) => error!("Error occured: {}", e),
     }
+
+    game.quit();
 }
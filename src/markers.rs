@@ -0,0 +1,417 @@
+use super::camera::grayscale;
+use std::cmp::Ordering;
+use std::f32::consts::PI;
+
+/// How many cells there are along each side of a marker. The outermost ring
+/// of cells is a black border, the inner cells carry the code.
+const GRID: usize = 6;
+
+/// How many cells there are along each side of the code inside the border.
+const CODE: usize = GRID - 2;
+
+/// Dark blobs with fewer pixels than this are too small to be read reliably,
+/// that is less than two pixels per cell.
+const MIN_MARKER_AREA: usize = (GRID * 2) * (GRID * 2);
+
+/// A pixel is considered dark, that is printed black, if its grayscale is
+/// lower than this fraction of the average grayscale of the frame.
+const DARK_RATIO: f32 = 0.6;
+
+/// A marker found in a camera frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Marker {
+    /// The number encoded in the marker.
+    pub id: u8,
+
+    /// The x and y coordinate of the marker's center.
+    pub position: (f32, f32),
+
+    /// Which way is the top edge of the marker facing. The angle is in
+    /// radians, zero is the positive x axis and it grows towards positive y.
+    pub heading: f32,
+}
+
+/// Finds all readable markers in an RGB frame of given width and height.
+///
+/// Each marker is a black square with a 4x4 code inside. White cells are
+/// ones. The code has the top left corner cell white and the other three
+/// corner cells black, which tells us the orientation. The remaining twelve
+/// cells are the id (8 bits) followed by a checksum (4 bits), read row by row.
+///
+/// ```
+/// ######
+/// #1..c#
+/// #....#
+/// #....#
+/// #0..0#
+/// ######
+/// ```
+pub fn detect(frame: &[u8], width: usize, height: usize) -> Vec<Marker> {
+    debug_assert_eq!(frame.len(), width * height * 3);
+    let gray: Vec<u8> = frame.chunks(3).map(grayscale).collect();
+    let average =
+        gray.iter().fold(0usize, |sum, g| sum + *g as usize) / gray.len();
+    let threshold = (average as f32 * DARK_RATIO) as u8;
+    let is_dark = |x: usize, y: usize| gray[y * width + x] < threshold;
+
+    let mut visited = vec![false; gray.len()];
+    let mut markers = Vec::new();
+    let mut stack = Vec::new();
+
+    for start in 0..gray.len() {
+        if visited[start] || gray[start] >= threshold {
+            continue;
+        }
+
+        // Flood fills the dark blob and remembers its pixels.
+        let mut pixels = Vec::new();
+        visited[start] = true;
+        stack.push(start);
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % width, i / width);
+            pixels.push((x as f32, y as f32));
+
+            let mut visit = |x: usize, y: usize| {
+                let j = y * width + x;
+                if !visited[j] && is_dark(x, y) {
+                    visited[j] = true;
+                    stack.push(j);
+                }
+            };
+            if x > 0 {
+                visit(x - 1, y);
+            }
+            if x + 1 < width {
+                visit(x + 1, y);
+            }
+            if y > 0 {
+                visit(x, y - 1);
+            }
+            if y + 1 < height {
+                visit(x, y + 1);
+            }
+        }
+
+        if pixels.len() < MIN_MARKER_AREA {
+            continue;
+        }
+
+        if let Some(marker) = Corners::fit(&pixels).read(&is_dark) {
+            markers.push(marker);
+        }
+    }
+
+    markers
+}
+
+// Reads the id from a 4x4 code, if the code is in the upright orientation.
+fn decode(code: &[[bool; CODE]; CODE]) -> Option<u8> {
+    let last = CODE - 1;
+    if !code[0][0] || code[0][last] || code[last][0] || code[last][last] {
+        return None;
+    }
+
+    let mut payload = 0u16;
+    for (y, row) in code.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            if !is_corner(x, y) {
+                payload = payload << 1 | *cell as u16;
+            }
+        }
+    }
+
+    let id = (payload >> 4) as u8;
+    if checksum(id) == (payload & 0xf) as u8 {
+        Some(id)
+    } else {
+        None
+    }
+}
+
+// Four bits that catch a single misread cell of the id.
+fn checksum(id: u8) -> u8 {
+    (id >> 4) ^ (id & 0xf) ^ 0b1010
+}
+
+fn is_corner(x: usize, y: usize) -> bool {
+    (x == 0 || x == CODE - 1) && (y == 0 || y == CODE - 1)
+}
+
+// Rotates the code by 90 degrees clockwise.
+fn rotate(code: &[[bool; CODE]; CODE]) -> [[bool; CODE]; CODE] {
+    let mut rotated = [[false; CODE]; CODE];
+    for (y, row) in code.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            rotated[x][CODE - 1 - y] = *cell;
+        }
+    }
+    rotated
+}
+
+// The four corners of a blob which is a square, clockwise in the frame and
+// starting at any of them.
+struct Corners([(f32, f32); 4]);
+
+impl Corners {
+    // Fits a square onto the pixels of a blob, however it's rotated. The
+    // pixel farthest from the center of the blob is a corner and the pixel
+    // farthest from that one is the opposite corner. The other two corners
+    // are the pixels farthest from the diagonal between them on either side.
+    fn fit(pixels: &[(f32, f32)]) -> Self {
+        let count = pixels.len() as f32;
+        let (sum_x, sum_y) = pixels
+            .iter()
+            .fold((0.0, 0.0), |(sum_x, sum_y), (x, y)| (sum_x + x, sum_y + y));
+        let center = (sum_x / count, sum_y / count);
+
+        let farthest = |rate: &dyn Fn((f32, f32)) -> f32| {
+            pixels
+                .iter()
+                .copied()
+                .max_by(|a, b| {
+                    rate(*a).partial_cmp(&rate(*b)).unwrap_or(Ordering::Equal)
+                })
+                .unwrap_or(center)
+        };
+        let distance = |(x, y): (f32, f32), (to_x, to_y): (f32, f32)| {
+            (x - to_x).powi(2) + (y - to_y).powi(2)
+        };
+        let first = farthest(&|pixel| distance(pixel, center));
+        let opposite = farthest(&|pixel| distance(pixel, first));
+
+        // How far is the pixel to the left of the diagonal, as seen from the
+        // first corner towards the opposite one.
+        let (along_x, along_y) = (opposite.0 - first.0, opposite.1 - first.1);
+        let left = |(x, y): (f32, f32)| {
+            along_y * (x - first.0) - along_x * (y - first.1)
+        };
+
+        Corners([
+            first,
+            farthest(&|pixel| left(pixel)),
+            opposite,
+            farthest(&|pixel| -left(pixel)),
+        ])
+    }
+
+    // Samples the middle of each cell of the square and decodes the marker.
+    fn read(&self, is_dark: &dyn Fn(usize, usize) -> bool) -> Option<Marker> {
+        let corners = self.0;
+
+        // The corner points are the outer pixels of the square, hence the
+        // half pixel padding so that the cells are evenly spread.
+        let cell = |col: usize, row: usize| -> (f32, f32) {
+            let u = (col as f32 + 0.5) / GRID as f32;
+            let v = (row as f32 + 0.5) / GRID as f32;
+            let [tl, tr, br, bl] = corners;
+            (
+                (1.0 - u) * (1.0 - v) * tl.0
+                    + u * (1.0 - v) * tr.0
+                    + u * v * br.0
+                    + (1.0 - u) * v * bl.0
+                    + 0.5,
+                (1.0 - u) * (1.0 - v) * tl.1
+                    + u * (1.0 - v) * tr.1
+                    + u * v * br.1
+                    + (1.0 - u) * v * bl.1
+                    + 0.5,
+            )
+        };
+        let is_dark_cell = |col: usize, row: usize| {
+            let (x, y) = cell(col, row);
+            is_dark(x as usize, y as usize)
+        };
+
+        // The whole border has to be black.
+        for i in 0..GRID {
+            let last = GRID - 1;
+            if !is_dark_cell(i, 0)
+                || !is_dark_cell(i, last)
+                || !is_dark_cell(0, i)
+                || !is_dark_cell(last, i)
+            {
+                return None;
+            }
+        }
+
+        let mut code = [[false; CODE]; CODE];
+        for (y, row) in code.iter_mut().enumerate() {
+            for (x, bit) in row.iter_mut().enumerate() {
+                *bit = !is_dark_cell(x + 1, y + 1);
+            }
+        }
+
+        // Tries all four orientations. Once the code is upright, the corner
+        // which was rotated into the top left position is the marker's top
+        // left corner.
+        for turns in 0..4 {
+            if let Some(id) = decode(&code) {
+                // Each clockwise turn of the code means that the marker's top
+                // left corner lies one corner further clockwise.
+                let tl = corners[(4 - turns) % 4];
+                let tr = corners[(5 - turns) % 4];
+                let br = corners[(6 - turns) % 4];
+                let bl = corners[(7 - turns) % 4];
+                let top = ((tl.0 + tr.0) / 2.0, (tl.1 + tr.1) / 2.0);
+                let bottom = ((bl.0 + br.0) / 2.0, (bl.1 + br.1) / 2.0);
+
+                return Some(Marker {
+                    id,
+                    position: (
+                        (tl.0 + tr.0 + br.0 + bl.0) / 4.0 + 0.5,
+                        (tl.1 + tr.1 + br.1 + bl.1) / 4.0 + 0.5,
+                    ),
+                    heading: normalize_angle(
+                        (top.1 - bottom.1).atan2(top.0 - bottom.0),
+                    ),
+                });
+            }
+            code = rotate(&code);
+        }
+
+        None
+    }
+}
+
+/// Puts the angle into interval <0; 2PI).
+pub fn normalize_angle(angle: f32) -> f32 {
+    let angle = angle % (2.0 * PI);
+    if angle < 0.0 {
+        angle + 2.0 * PI
+    } else {
+        angle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Draws the code of a marker with given id. Returns rows of the 4x4 code
+    // where `true` stands for a white cell.
+    fn encode(id: u8) -> [[bool; CODE]; CODE] {
+        let payload = u16::from(id) << 4 | u16::from(checksum(id));
+        let mut code = [[false; CODE]; CODE];
+        code[0][0] = true;
+        // All cells but the corners carry the id and the checksum.
+        let mut bit = CODE * CODE - 4;
+        for (y, row) in code.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                if !is_corner(x, y) {
+                    bit -= 1;
+                    *cell = payload >> bit & 1 == 1;
+                }
+            }
+        }
+
+        code
+    }
+
+    const WIDTH: usize = 160;
+    const HEIGHT: usize = 120;
+
+    // Renders a white frame with a marker of given id. The marker has its
+    // center at given position, each cell is `cell` pixels large and the
+    // marker is rotated by `angle` radians clockwise.
+    fn render(id: u8, (cx, cy): (f32, f32), cell: f32, angle: f32) -> Vec<u8> {
+        let code = encode(id);
+        let half = cell * GRID as f32 / 2.0;
+        let mut frame = vec![255; WIDTH * HEIGHT * 3];
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                // Rotates the pixel back into the marker's own coordinates.
+                let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                let u = dx * angle.cos() + dy * angle.sin() + half;
+                let v = -dx * angle.sin() + dy * angle.cos() + half;
+                if u < 0.0 || v < 0.0 || u >= half * 2.0 || v >= half * 2.0 {
+                    continue;
+                }
+
+                let (col, row) = ((u / cell) as usize, (v / cell) as usize);
+                let border = col == 0 || row == 0 || col == 5 || row == 5;
+                if border || !code[row - 1][col - 1] {
+                    let i = (y * WIDTH + x) * 3;
+                    frame[i..i + 3].copy_from_slice(&[20, 20, 20]);
+                }
+            }
+        }
+        frame
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        for id in 0..=255 {
+            assert_eq!(Some(id), decode(&encode(id)));
+        }
+    }
+
+    #[test]
+    fn test_rotated_code_is_not_upright() {
+        for id in 0..=255 {
+            let code = rotate(&encode(id));
+            assert_eq!(None, decode(&code));
+        }
+    }
+
+    #[test]
+    fn test_detect_upright_marker() {
+        let frame = render(42, (50.0, 60.0), 6.0, 0.0);
+        let markers = detect(&frame, WIDTH, HEIGHT);
+        assert_eq!(1, markers.len());
+        assert_eq!(42, markers[0].id);
+        assert!((markers[0].position.0 - 50.0).abs() < 1.0);
+        assert!((markers[0].position.1 - 60.0).abs() < 1.0);
+        // The top edge faces the negative y.
+        assert!((markers[0].heading - PI * 1.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_detect_rotated_markers() {
+        for quarter in 0..4 {
+            let angle = quarter as f32 * PI / 2.0 + 0.3;
+            let frame = render(7, (80.0, 60.0), 7.0, angle);
+            let markers = detect(&frame, WIDTH, HEIGHT);
+            assert_eq!(1, markers.len());
+            assert_eq!(7, markers[0].id);
+            let expected = normalize_angle(PI * 1.5 + angle);
+            assert!(
+                (markers[0].heading - expected).abs() < 0.1,
+                "{} != {}",
+                markers[0].heading,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_detect_diagonal_markers() {
+        for quarter in 0..4 {
+            let angle = quarter as f32 * PI / 2.0 + PI / 4.0;
+            let frame = render(7, (80.0, 60.0), 7.0, angle);
+            let markers = detect(&frame, WIDTH, HEIGHT);
+            assert_eq!(1, markers.len());
+            assert_eq!(7, markers[0].id);
+            assert!((markers[0].position.0 - 80.0).abs() < 1.0);
+            assert!((markers[0].position.1 - 60.0).abs() < 1.0);
+            let expected = normalize_angle(PI * 1.5 + angle);
+            assert!(
+                (markers[0].heading - expected).abs() < 0.1,
+                "{} != {}",
+                markers[0].heading,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_ignore_plain_square() {
+        let mut frame = vec![255; WIDTH * HEIGHT * 3];
+        for y in 20..60 {
+            for x in 20..60 {
+                let i = (y * WIDTH + x) * 3;
+                frame[i..i + 3].copy_from_slice(&[0, 0, 0]);
+            }
+        }
+        assert!(detect(&frame, WIDTH, HEIGHT).is_empty());
+    }
+}
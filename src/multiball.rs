@@ -0,0 +1,118 @@
+use super::TICKS_PER_SECOND;
+use std::env;
+
+/// Who scores when one of several balls in play leaves the playfield.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scoring {
+    /// Every ball which gets past a paddle scores a point. The rally goes on
+    /// until the last ball is out.
+    EachBall,
+
+    /// Balls which get past a paddle just disappear while others are still
+    /// in play. Only the last ball out scores.
+    LastBall,
+}
+
+/// When do more balls join a rally.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Multiball {
+    /// Another ball joins after each this many hits of the paddles.
+    pub after_hits: Option<u32>,
+
+    /// Another ball joins every this many ticks of a rally.
+    pub every: Option<u32>,
+
+    /// At most this many balls take part in a rally, the served one
+    /// included. The rally ends once they are all out.
+    pub max_balls: usize,
+
+    /// Who scores when a ball leaves the playfield.
+    pub scoring: Scoring,
+}
+
+impl Default for Multiball {
+    /// One ball at a time.
+    fn default() -> Self {
+        Self {
+            after_hits: None,
+            every: None,
+            max_balls: 3,
+            scoring: Scoring::EachBall,
+        }
+    }
+}
+
+impl Multiball {
+    /// Reads when do more balls join from `PONG_MULTIBALL_HITS` and
+    /// `PONG_MULTIBALL_SECONDS` env vars, neither is set by default. At most
+    /// `PONG_MAX_BALLS` balls (default 3) take part in a rally and
+    /// `PONG_MULTIBALL_SCORING` is either "each" (default) or "last".
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let seconds: Option<f32> =
+            env::var("PONG_MULTIBALL_SECONDS").ok().map(|value| {
+                value.parse().expect("Invalid PONG_MULTIBALL_SECONDS")
+            });
+        let scoring = match env::var("PONG_MULTIBALL_SCORING")
+            .as_ref()
+            .map(String::as_str)
+        {
+            Err(_) | Ok("each") => Scoring::EachBall,
+            Ok("last") => Scoring::LastBall,
+            Ok(other) => panic!("Unknown multiball scoring {}", other),
+        };
+
+        Self {
+            after_hits: env::var("PONG_MULTIBALL_HITS").ok().map(|value| {
+                value.parse().expect("Invalid PONG_MULTIBALL_HITS")
+            }),
+            every: seconds
+                .map(|seconds| (seconds * TICKS_PER_SECOND as f32) as u32),
+            max_balls: env::var("PONG_MAX_BALLS")
+                .map(|value| value.parse().expect("Invalid PONG_MAX_BALLS"))
+                .unwrap_or(default.max_balls),
+            scoring,
+        }
+    }
+
+    /// Whether another ball joins a rally which has lasted given number of
+    /// ticks, in which the paddles have just been hit given number of times,
+    /// if at all, and which given number of balls have taken part in.
+    pub fn spawns(self, ticks: u32, hits: Option<u32>, balls: usize) -> bool {
+        if balls >= self.max_balls {
+            return false;
+        }
+
+        let on_time = self.every.map_or(false, |every| {
+            every > 0 && ticks > 0 && ticks % every == 0
+        });
+        let on_hit = match (self.after_hits, hits) {
+            (Some(after), Some(hits)) => after > 0 && hits % after == 0,
+            _ => false,
+        };
+
+        on_time || on_hit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spawns() {
+        let multiball = Multiball {
+            after_hits: Some(4),
+            every: Some(600),
+            ..Default::default()
+        };
+        assert!(!multiball.spawns(1, None, 1));
+        assert!(!multiball.spawns(1, Some(3), 1));
+        assert!(multiball.spawns(1, Some(4), 1));
+        assert!(multiball.spawns(1, Some(8), 2));
+        assert!(!multiball.spawns(1, Some(8), 3));
+        assert!(multiball.spawns(600, None, 2));
+        assert!(!multiball.spawns(601, None, 2));
+        assert!(!Multiball::default().spawns(600, Some(4), 1));
+    }
+}
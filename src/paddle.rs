@@ -1,22 +1,142 @@
-use super::WINDOW_SIZE;
+use super::{DT, WINDOW_SIZE};
 use ggez::graphics::{
     draw, BlendMode, Color, DrawMode, DrawParam, Drawable, MeshBuilder, Rect,
     BLACK,
 };
 use ggez::{Context, GameResult};
-use std::sync::{Arc, Mutex};
+use serde::Deserialize;
 
 /// Default paddle size. (width, height)
 pub const PADDLE_SIZE: (f32, f32) = (100.0, 10.0);
 
+/// How far from their edge does the front paddle of a doubles team play,
+/// unless the level says otherwise.
+pub const FRONT_OFFSET: f32 = 200.0;
+
+/// How much does each tick's motion of the paddle weigh in its velocity. The
+/// rest is the velocity from before, which smooths jittery input such as the
+/// camera.
+pub const VELOCITY_SMOOTHING: f32 = 0.5;
+
+/// How fast can a hand move the paddle, in pixels per second. A paddle which
+/// moves faster, e.g. when the mouse jumps or the camera loses track of the
+/// hand for a moment, counts as moving this fast.
+pub const MAX_HAND_SPEED: f32 = 1500.0;
+
+/// Which edge of the window does a player sit by. The players sit by the
+/// top, the bottom, the left and the right edge in the order of their ids.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Side {
+    pub const ALL: [Side; 4] =
+        [Side::Top, Side::Bottom, Side::Left, Side::Right];
+
+    /// Returns the side of the player with given id.
+    pub fn of(player_id: u8) -> Self {
+        Side::ALL[player_id as usize]
+    }
+
+    /// Returns the id of the player who sits by this side.
+    pub fn player_id(self) -> u8 {
+        Side::ALL
+            .iter()
+            .position(|side| *side == self)
+            .expect("Every side is in Side::ALL") as u8
+    }
+
+    /// Whether the paddles on this side move along the y axis.
+    pub fn is_vertical(self) -> bool {
+        self == Side::Left || self == Side::Right
+    }
+
+    /// Returns the coordinate of a point, or a vector, along the edge.
+    pub fn along(self, (x, y): (f32, f32)) -> f32 {
+        if self.is_vertical() {
+            y
+        } else {
+            x
+        }
+    }
+
+    /// Returns the coordinate of a point, or a vector, across the edge.
+    pub fn across(self, (x, y): (f32, f32)) -> f32 {
+        if self.is_vertical() {
+            x
+        } else {
+            y
+        }
+    }
+
+    /// Returns the point, or the vector, with given coordinates along and
+    /// across the edge.
+    pub fn point(self, along: f32, across: f32) -> (f32, f32) {
+        if self.is_vertical() {
+            (across, along)
+        } else {
+            (along, across)
+        }
+    }
+
+    /// Returns 1 if going away from the edge increases the coordinate
+    /// across it, -1 otherwise.
+    pub fn away(self) -> f32 {
+        match self {
+            Side::Top | Side::Left => 1.0,
+            Side::Bottom | Side::Right => -1.0,
+        }
+    }
+
+    /// How long is the edge.
+    pub fn length(self) -> f32 {
+        self.along(WINDOW_SIZE)
+    }
+}
+
+/// Where does a paddle move. By default it moves along its whole edge of the
+/// window.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Track {
+    /// How far from its edge of the window is the paddle.
+    pub offset: f32,
+
+    /// The lowest coordinate along the edge the paddle reaches, which is x
+    /// for the top and the bottom edge and y for the others.
+    pub from: f32,
+
+    /// The highest coordinate along the edge the paddle reaches, at most the
+    /// length of the edge.
+    pub to: f32,
+}
+
+impl Default for Track {
+    fn default() -> Self {
+        Self {
+            offset: 0.0,
+            from: 0.0,
+            to: WINDOW_SIZE.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Paddle {
     /// Which player controls the paddle.
     pub player_id: u8,
 
-    /// Width of the rectangle representing the paddle.
+    /// Which edge does the paddle move along.
+    pub side: Side,
+
+    /// Width of the rectangle representing the paddle, along its edge.
     pub width: f32,
 
-    /// Height of the rectangle representing the paddle.
+    /// Height of the rectangle representing the paddle, across its edge.
     pub height: f32,
 
     /// How many times has the paddle fail to bounce the ball.
@@ -25,31 +145,145 @@ pub struct Paddle {
     /// Paddle colour will be used to draw the ball in the next tick.
     pub color: Color,
 
-    /// Pointer to the mutex which updates the paddle's position.
-    pub x: Arc<Mutex<u32>>,
+    /// The coordinate of the paddle's center along its edge, which is x for
+    /// the top and the bottom edge and y for the others. The paddle's
+    /// controller decides where it goes.
+    pub x: f32,
+
+    /// Where was the paddle's center before the last tick. The paddle is
+    /// drawn in between the two positions if the frame comes in between
+    /// ticks.
+    pub previous_x: f32,
+
+    /// How fast is the paddle moving in pixels per second, positive to the
+    /// right, or down along the left and the right edge.
+    pub velocity: f32,
+
+    /// Where does the paddle move.
+    pub track: Track,
 }
 
 impl Paddle {
     /// Spawns new player
-    pub fn new(player_id: u8, x: Arc<Mutex<u32>>) -> Self {
+    pub fn new(player_id: u8) -> Self {
+        let side = Side::of(player_id);
         Paddle {
-            x,
+            x: side.length() / 2.0,
+            previous_x: side.length() / 2.0,
+            velocity: 0.0,
             player_id,
+            side,
             deaths: 0,
             color: BLACK,
             width: PADDLE_SIZE.0,
             height: PADDLE_SIZE.1,
+            track: Track::default(),
         }
     }
 
+    /// Moves the paddle to given edge, e.g. to the edge of the player's team
+    /// in doubles.
+    pub fn with_side(mut self, side: Side) -> Self {
+        self.side = side;
+        let track = self.track;
+        self.with_track(track)
+    }
+
+    /// Puts the paddle on given track and moves it to the middle of it.
+    pub fn with_track(mut self, track: Track) -> Self {
+        self.track = track;
+        self.x = (track.from + self.end()) / 2.0;
+        self.previous_x = self.x;
+        self
+    }
+
     /// Returns position of the top left corner of the paddle.
     pub fn position(&self) -> (f32, f32) {
-        (
-            ((*self.x.lock().unwrap()) as f32 - self.width / 2.0)
-                .min(WINDOW_SIZE.0 - self.width)
-                .max(0.0),
-            self.player_id as f32 * (WINDOW_SIZE.1 - self.height),
-        )
+        self.corner_at(self.x)
+    }
+
+    /// Returns position of the top left corner of the paddle if its center
+    /// was at given coordinate along its edge.
+    pub fn corner_at(&self, x: f32) -> (f32, f32) {
+        let along = (x - self.width / 2.0)
+            .min(self.end() - self.width)
+            .max(self.track.from);
+        let across = if self.side.away() > 0.0 {
+            self.track.offset
+        } else {
+            self.side.across(WINDOW_SIZE) - self.height - self.track.offset
+        };
+        self.side.point(along, across)
+    }
+
+    /// Returns the width and the height of the paddle as drawn in the
+    /// window, which are swapped for the left and the right edge.
+    pub fn size(&self) -> (f32, f32) {
+        self.side.point(self.width, self.height)
+    }
+
+    /// Moves the paddle's center to given coordinate along its edge, but
+    /// keeps the whole paddle on its track.
+    pub fn move_to(&mut self, x: f32) {
+        self.x = x
+            .min(self.end() - self.width / 2.0)
+            .max(self.track.from + self.width / 2.0);
+    }
+
+    /// Returns the paddle as seen by its player from the top or the bottom
+    /// edge, see `Ball::seen_from`. Paddles on the left are seen from the
+    /// top and paddles on the right from the bottom.
+    pub fn seen(&self) -> Self {
+        let side = match self.side {
+            Side::Top | Side::Left => Side::Top,
+            Side::Bottom | Side::Right => Side::Bottom,
+        };
+        Self {
+            side,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the paddle as if its edge was flipped end to end.
+    pub fn mirrored(&self) -> Self {
+        let length = self.side.length();
+        Self {
+            x: length - self.x,
+            previous_x: length - self.previous_x,
+            velocity: -self.velocity,
+            track: Track {
+                from: length - self.end(),
+                to: length - self.track.from,
+                ..self.track
+            },
+            ..self.clone()
+        }
+    }
+
+    /// Updates the velocity by how much has the paddle moved since the last
+    /// tick, up to `MAX_HAND_SPEED`.
+    pub fn track_velocity(&mut self) {
+        let velocity = ((self.x - self.previous_x) / DT)
+            .max(-MAX_HAND_SPEED)
+            .min(MAX_HAND_SPEED);
+        self.velocity += (velocity - self.velocity) * VELOCITY_SMOOTHING;
+    }
+
+    /// Returns the coordinate across the edge at which the center of a ball
+    /// with given radius touches the face of the paddle. That's the y
+    /// coordinate for the top and the bottom edge.
+    pub fn ball_line(&self, radius: f32) -> f32 {
+        let across = self.side.across(self.position());
+        if self.side.away() > 0.0 {
+            across + self.height + radius
+        } else {
+            across - radius
+        }
+    }
+
+    // Returns the highest coordinate along the edge the paddle reaches.
+    fn end(&self) -> f32 {
+        self.track.to.min(self.side.length())
     }
 }
 
@@ -57,9 +291,9 @@ impl Drawable for Paddle {
     /// Draws the ball on the canvas.
     fn draw(&self, ctx: &mut Context, param: DrawParam) -> GameResult<()> {
         let (paddle_x, paddle_y) = self.position();
+        let (width, height) = self.size();
 
-        let paddle_shape =
-            Rect::new(paddle_x, paddle_y, self.width, self.height);
+        let paddle_shape = Rect::new(paddle_x, paddle_y, width, height);
 
         let paddle_mesh = MeshBuilder::new()
             .rectangle(DrawMode::fill(), paddle_shape, self.color)
@@ -71,12 +305,13 @@ impl Drawable for Paddle {
     /// Creates a bounding box around the paddle..
     fn dimensions(&self, _ctx: &mut Context) -> Option<Rect> {
         let (paddle_x, paddle_y) = self.position();
+        let (width, height) = self.size();
 
         Some(Rect::new(
             paddle_x,
             paddle_y,
-            paddle_x + self.width,
-            paddle_y + self.height,
+            paddle_x + width,
+            paddle_y + height,
         ))
     }
 
@@ -91,3 +326,95 @@ impl Drawable for Paddle {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track_velocity() {
+        let mut paddle = Paddle::new(0);
+        for _ in 0..20 {
+            paddle.previous_x = paddle.x;
+            paddle.move_to(paddle.x + 5.0);
+            paddle.track_velocity();
+        }
+        assert!((paddle.velocity - 5.0 / DT).abs() < 0.1);
+
+        paddle.previous_x = paddle.x;
+        paddle.track_velocity();
+        assert!((paddle.velocity - 2.5 / DT).abs() < 0.1);
+
+        // A paddle which jumps across its whole edge isn't faster than a
+        // hand.
+        paddle.move_to(0.0);
+        paddle.previous_x = paddle.x;
+        paddle.move_to(WINDOW_SIZE.0);
+        paddle.track_velocity();
+        assert!(paddle.velocity <= MAX_HAND_SPEED);
+    }
+
+    #[test]
+    fn test_track() {
+        let track = Track {
+            offset: 40.0,
+            from: 200.0,
+            to: 600.0,
+        };
+        let mut paddle = Paddle::new(1).with_track(track);
+        assert!((paddle.x - 400.0).abs() < 0.01);
+        assert!((paddle.position().1 - (WINDOW_SIZE.1 - 50.0)).abs() < 0.01);
+        assert!(
+            (Paddle::new(0).with_track(track).position().1 - 40.0).abs() < 0.01
+        );
+
+        paddle.move_to(0.0);
+        assert!((paddle.position().0 - 200.0).abs() < 0.01);
+        paddle.move_to(1000.0);
+        assert!((paddle.position().0 + paddle.width - 600.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_sides() {
+        let left = Paddle::new(2);
+        assert_eq!(Side::Left, left.side);
+        assert!((left.x - WINDOW_SIZE.1 / 2.0).abs() < 0.01);
+        assert_eq!((PADDLE_SIZE.1, PADDLE_SIZE.0), left.size());
+        let (x, y) = left.position();
+        assert!(x.abs() < 0.01);
+        assert!((y - (WINDOW_SIZE.1 - PADDLE_SIZE.0) / 2.0).abs() < 0.01);
+        assert!((left.ball_line(13.0) - (PADDLE_SIZE.1 + 13.0)).abs() < 0.01);
+
+        let mut right = Paddle::new(3);
+        right.move_to(WINDOW_SIZE.0);
+        let (x, y) = right.position();
+        assert!((x - (WINDOW_SIZE.0 - PADDLE_SIZE.1)).abs() < 0.01);
+        assert!((y - (WINDOW_SIZE.1 - PADDLE_SIZE.0)).abs() < 0.01);
+        assert!(
+            (right.ball_line(13.0) - (WINDOW_SIZE.0 - PADDLE_SIZE.1 - 13.0))
+                .abs()
+                < 0.01
+        );
+        assert_eq!(Side::Bottom, right.seen().side);
+    }
+
+    #[test]
+    fn test_mirrored() {
+        let mut bottom = Paddle::new(1);
+        bottom.move_to(100.0);
+        assert!((bottom.mirrored().x - (WINDOW_SIZE.0 - 100.0)).abs() < 0.01);
+
+        // The left edge is flipped end to end, not by the width of the
+        // window, and the paddle can still reach both of its ends.
+        let mut left = Paddle::new(2);
+        left.move_to(100.0);
+        let mut mirrored = left.mirrored();
+        assert!((mirrored.x - (WINDOW_SIZE.1 - 100.0)).abs() < 0.01);
+        mirrored.move_to(0.0);
+        assert!((mirrored.x - PADDLE_SIZE.0 / 2.0).abs() < 0.01);
+        mirrored.move_to(WINDOW_SIZE.1);
+        assert!(
+            (mirrored.x - (WINDOW_SIZE.1 - PADDLE_SIZE.0 / 2.0)).abs() < 0.01
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use super::Transition;
+use super::{frame_time, ignore_serves, Command, Paused, Phase, Rematch};
+use crate::pong::Pong;
+use crate::scoreboard::{self, draw_facing};
+use crate::WINDOW_SIZE;
+use ggez::graphics::BLACK;
+use ggez::{Context, GameResult};
+
+/// How large is the announcement of the winner.
+const RESULT_SIZE: f32 = 96.0;
+
+/// How many seconds is the winner announced for before a rematch is offered.
+const GAME_OVER_SECONDS: f32 = 5.0;
+
+/// One of the players has won the match. The winner is announced along with
+/// the final score.
+pub struct GameOver {
+    // How many seconds until the players are offered a rematch.
+    count_down: f32,
+}
+
+impl Default for GameOver {
+    fn default() -> Self {
+        Self {
+            count_down: GAME_OVER_SECONDS,
+        }
+    }
+}
+
+impl Phase for GameOver {
+    fn enter(&mut self, state: &mut Pong) {
+        info!("Match over, {:?}", state.game);
+        state.save_replay();
+        state.stop_robot();
+    }
+
+    /// Counts down until the players are offered a rematch. Serves pressed
+    /// in the meantime are thrown away, so that a late press doesn't start
+    /// the rematch right away.
+    fn update(
+        &mut self,
+        state: &mut Pong,
+        ctx: &mut Context,
+    ) -> GameResult<Transition> {
+        ignore_serves(state);
+
+        self.count_down -= frame_time(ctx);
+        if self.count_down <= 0.0 {
+            Ok(Transition::To(Box::new(Rematch)))
+        } else {
+            Ok(Transition::Stay)
+        }
+    }
+
+    fn draw(&mut self, state: &mut Pong, ctx: &mut Context) -> GameResult<()> {
+        draw_result(state, ctx)
+    }
+
+    fn command(&mut self, state: &mut Pong, command: Command) -> Transition {
+        reopen(state, command)
+    }
+}
+
+/// Tells each player whether they have won and the final score from their
+/// side of the table. A lone player who hasn't won is told the game is over.
+pub fn draw_result(state: &Pong, ctx: &mut Context) -> GameResult<()> {
+    let alone = state.game.alone;
+    for player_id in 0..state.game.players {
+        if alone.map_or(false, |alone| alone != player_id) {
+            continue;
+        }
+        let result = if state.game.winner == Some(player_id) {
+            "YOU WIN"
+        } else if alone.is_some() {
+            "GAME OVER"
+        } else {
+            "YOU LOSE"
+        };
+        let content = format!(
+            "{}  {}",
+            result,
+            scoreboard::score(&state.game, player_id)
+        );
+        let text = scoreboard::text(content, RESULT_SIZE);
+        let (width, _) = text.dimensions(ctx);
+        let position =
+            ((WINDOW_SIZE.0 - width as f32) / 2.0, WINDOW_SIZE.1 * 0.6);
+        draw_facing(ctx, &text, player_id, position, BLACK)?;
+    }
+
+    Ok(())
+}
+
+/// Taking back the point which has won the match, or resetting the score,
+/// brings the match back paused so that the players can get ready.
+pub fn reopen(state: &mut Pong, command: Command) -> Transition {
+    match command {
+        Command::Undo => {
+            if state.undo_point() {
+                Transition::To(Box::new(Paused))
+            } else {
+                Transition::Stay
+            }
+        }
+        Command::ResetScore => {
+            state.reset_score();
+            Transition::To(Box::new(Paused))
+        }
+        _ => Transition::Stay,
+    }
+}
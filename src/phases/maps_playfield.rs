@@ -1,37 +1,231 @@
-use super::Phase;
+use super::{frame_time, Command, Phase, PlaysPong, Transition};
+use crate::camera::Calibration;
 use crate::pong::Pong;
+use crate::scoreboard::{self, draw_facing};
+use crate::WINDOW_SIZE;
+use ggez::graphics::{self, Color, DrawParam, Mesh, BLACK};
+use ggez::nalgebra::Point2;
 use ggez::{Context, GameResult};
-use std::thread;
-use std::time::Duration;
-
-pub fn update(state: &mut Pong) -> GameResult<()> {
-    if let Phase::MapsPlayfield { ref mut count_down } = state.phase {
-        if *count_down == 0 {
-            info!("Taking a snapshot of the playfield before the game.");
-            state
-                .camera
-                .as_mut()
-                .expect("The game has not begun yet, the camera object must be present.")
-                .map_playfield();
-            state.phase = Phase::PlaysPong;
-            state
-                .camera
-                .take()
-                .unwrap()
-                .start_capturing()
-                .expect("Cannot spawn camera thread");
-        } else {
-            debug!("Will take a snapshot of the field in {}", count_down);
-            *count_down -= 1;
+
+/// How many seconds does the count down before the camera takes a snapshot
+/// of the playfield take.
+const COUNT_DOWN_SECONDS: f32 = 3.0;
+
+/// How many seconds is the outcome of a snapshot shown for.
+const OUTCOME_SECONDS: f32 = 2.0;
+
+/// After this many snapshots with something on the table, the last one is
+/// taken anyway, so that the game doesn't get stuck on a stained table.
+const MAX_ATTEMPTS: u32 = 5;
+
+/// How large are the digits of the count down. They are projected onto the
+/// table and must be readable from a distance.
+const COUNT_DOWN_SIZE: f32 = 240.0;
+
+/// How large are the instructions for the players.
+const INSTRUCTION_SIZE: f32 = 48.0;
+
+/// How tall is the preview of the snapshot.
+const PREVIEW_HEIGHT: f32 = 100.0;
+
+/// Counts down before the camera takes a snapshot of the empty table, which
+/// the camera thread compares its frames to. If something was still on the
+/// table, the snapshot is taken again.
+pub struct MapsPlayfield {
+    step: Step,
+
+    // How many snapshots have been taken.
+    attempts: u32,
+}
+
+enum Step {
+    // How many seconds until the camera takes a snapshot.
+    CountDown(f32),
+
+    // The snapshot was spoiled, e.g. by a hand. Another one is taken after
+    // given number of seconds.
+    Rejected(Calibration, f32),
+
+    // The snapshot is good, the game starts after given number of seconds.
+    Accepted(Calibration, f32),
+}
+
+impl Default for MapsPlayfield {
+    fn default() -> Self {
+        Self {
+            step: Step::CountDown(COUNT_DOWN_SECONDS),
+            attempts: 0,
+        }
+    }
+}
+
+impl Phase for MapsPlayfield {
+    /// Counts down and then takes a snapshot of the playfield. Without a
+    /// camera there's no playfield to map.
+    fn update(
+        &mut self,
+        state: &mut Pong,
+        ctx: &mut Context,
+    ) -> GameResult<Transition> {
+        let camera = match state.camera.as_mut() {
+            Some(camera) => camera,
+            None => return Ok(Transition::To(Box::new(PlaysPong))),
+        };
+
+        let dt = frame_time(ctx);
+        match &mut self.step {
+            Step::CountDown(left) if *left > dt => *left -= dt,
+            Step::CountDown(_) => {
+                info!("Taking a snapshot of the playfield before the game.");
+                let calibration = camera.map_playfield();
+                self.attempts += 1;
+                info!(
+                    "{:.0}% of the table is clear",
+                    calibration.uniformity * 100.0
+                );
+
+                self.step = if calibration.is_clear() {
+                    Step::Accepted(calibration, OUTCOME_SECONDS)
+                } else if self.attempts >= MAX_ATTEMPTS {
+                    warn!("The table isn't clear, playing anyway");
+                    Step::Accepted(calibration, OUTCOME_SECONDS)
+                } else {
+                    warn!("The table isn't clear, trying again");
+                    Step::Rejected(calibration, OUTCOME_SECONDS)
+                };
+            }
+            Step::Rejected(_, left) | Step::Accepted(_, left) if *left > dt => {
+                *left -= dt
+            }
+            Step::Rejected(..) => {
+                self.step = Step::CountDown(COUNT_DOWN_SECONDS);
+            }
+            Step::Accepted(..) => {
+                state
+                    .camera
+                    .take()
+                    .expect("The camera has mapped the playfield")
+                    .start_capturing()
+                    .expect("Cannot spawn camera thread");
+                return Ok(Transition::To(Box::new(PlaysPong)));
+            }
         }
-        thread::sleep(Duration::from_secs(1));
-    } else {
-        unreachable!("Run logic for mapping a playfield with wrong phase.");
+
+        Ok(Transition::Stay)
+    }
+
+    /// Tells the players to clear the table and counts down, then shows
+    /// how even the table looked to the camera.
+    fn draw(&mut self, state: &mut Pong, ctx: &mut Context) -> GameResult<()> {
+        let players = state.game.players;
+        match &self.step {
+            Step::CountDown(left) => {
+                let seconds = left.ceil().max(1.0) as u32;
+                draw_centered(
+                    ctx,
+                    players,
+                    seconds.to_string(),
+                    COUNT_DOWN_SIZE,
+                    0.55,
+                )?;
+                draw_centered(
+                    ctx,
+                    players,
+                    "Clear the table".to_string(),
+                    INSTRUCTION_SIZE,
+                    0.85,
+                )
+            }
+            Step::Rejected(calibration, _) => {
+                draw_preview(ctx, calibration)?;
+                draw_centered(
+                    ctx,
+                    players,
+                    format!(
+                        "Only {:.0}% of the table is clear, once more",
+                        calibration.uniformity * 100.0
+                    ),
+                    INSTRUCTION_SIZE,
+                    0.7,
+                )
+            }
+            Step::Accepted(calibration, _) => {
+                draw_preview(ctx, calibration)?;
+                draw_centered(
+                    ctx,
+                    players,
+                    format!(
+                        "Table mapped, {:.0}% clear",
+                        calibration.uniformity * 100.0
+                    ),
+                    INSTRUCTION_SIZE,
+                    0.7,
+                )
+            }
+        }
+    }
+
+    /// The players can change their handicaps before the first match.
+    fn command(&mut self, state: &mut Pong, command: Command) -> Transition {
+        if let Command::Handicap(player_id) = command {
+            state.switch_handicap(player_id);
+        }
+        Transition::Stay
+    }
+}
+
+// Draws the text in the middle of each player's half, at given fraction of
+// the window height from the top as seen by the player.
+fn draw_centered(
+    ctx: &mut Context,
+    players: u8,
+    content: String,
+    size: f32,
+    y: f32,
+) -> GameResult<()> {
+    let text = scoreboard::text(content, size);
+    let (width, height) = text.dimensions(ctx);
+    let position = (
+        (WINDOW_SIZE.0 - width as f32) / 2.0,
+        WINDOW_SIZE.1 * y - height as f32 / 2.0,
+    );
+    for player_id in 0..players {
+        draw_facing(ctx, &text, player_id, position, BLACK)?;
     }
 
     Ok(())
 }
 
-pub fn draw(_state: &mut Pong, _ctx: &mut Context) -> GameResult<()> {
+// Plots the gray of each column of the snapshot across its half of the
+// window. A flat line is an empty table, a dip or a bump is something on it.
+fn draw_preview(
+    ctx: &mut Context,
+    calibration: &Calibration,
+) -> GameResult<()> {
+    let color = Color::new(0.5, 0.5, 0.5, 1.0);
+    let halves = [WINDOW_SIZE.1 / 4.0, WINDOW_SIZE.1 * 3.0 / 4.0];
+    for (profile, middle) in calibration.profile.iter().zip(&halves) {
+        if profile.len() < 2 {
+            continue;
+        }
+
+        // The camera takes a mirror image rotated by 180 degrees, hence the
+        // top half of the frame is the bottom half of the window.
+        let last = (profile.len() - 1) as f32;
+        let points: Vec<_> = profile
+            .iter()
+            .enumerate()
+            .map(|(x, gray)| {
+                let offset = (f32::from(*gray) / 255.0 - 0.5) * PREVIEW_HEIGHT;
+                Point2::new(
+                    WINDOW_SIZE.0 * (1.0 - x as f32 / last),
+                    WINDOW_SIZE.1 - middle - offset,
+                )
+            })
+            .collect();
+        let line = Mesh::new_line(ctx, &points, 2.0, color)?;
+        graphics::draw(ctx, &line, DrawParam::default())?;
+    }
+
     Ok(())
 }
@@ -1,34 +1,84 @@
+mod game_over;
 mod maps_playfield;
-use crate::pong::Pong;
-use ggez::{Context, GameResult};
+mod paused;
 mod plays_pong;
+mod rematch;
 
-/// Classifies which phase is the game currently at. The enum is important for a
-/// correct game flow and camera calibration.
-pub enum Phase {
-    /// Renders cubes in top right and bottom left corners of the screen to map the
-    /// projected screen output to the camera input.
-    MapsPlayfield {
-        // How long until the camera takes a capture of the playfield.
-        count_down: usize,
-    },
-
-    /// In this stage the camera module is set up for both players and the game
-    /// begins by rendering the ball.
-    PlaysPong,
-}
+pub use game_over::GameOver;
+pub use maps_playfield::MapsPlayfield;
+pub use paused::Paused;
+pub use plays_pong::PlaysPong;
+pub use rematch::Rematch;
+
+use crate::pong::Pong;
+use ggez::{timer, Context, GameResult};
+
+/// A stage of the game flow, such as mapping the playfield or playing a
+/// match. The game is always in exactly one phase. Phases are driven by the
+/// ggez loop and must never block it, anything which takes time is counted
+/// down with the time of the frames.
+pub trait Phase {
+    /// Called when the game switches into this phase.
+    fn enter(&mut self, _state: &mut Pong) {}
+
+    /// Moves the phase along by one frame. Returns which phase comes next.
+    fn update(
+        &mut self,
+        state: &mut Pong,
+        ctx: &mut Context,
+    ) -> GameResult<Transition>;
 
-impl Phase {
-    pub fn update(state: &mut Pong) -> GameResult<()> {
-        match state.phase {
-            Phase::MapsPlayfield { .. } => maps_playfield::update(state),
-            Phase::PlaysPong => plays_pong::update(state),
-        }
+    /// Draws the phase. The window is cleared beforehand.
+    fn draw(&mut self, state: &mut Pong, ctx: &mut Context) -> GameResult<()>;
+
+    /// Handles a command of the operator. Returns which phase comes next.
+    /// Phases ignore the commands which make no sense in them.
+    fn command(&mut self, _state: &mut Pong, _command: Command) -> Transition {
+        Transition::Stay
     }
-    pub fn draw(state: &mut Pong, ctx: &mut Context) -> GameResult<()> {
-        match state.phase {
-            Phase::MapsPlayfield { .. } => maps_playfield::draw(state, ctx),
-            Phase::PlaysPong => plays_pong::draw(state, ctx),
-        }
+
+    /// Called when the game switches from this phase into another one.
+    fn exit(&mut self, _state: &mut Pong) {}
+}
+
+/// Which phase comes after the current one.
+pub enum Transition {
+    /// The game stays in the current phase.
+    Stay,
+
+    /// The game leaves the current phase and enters the given one.
+    To(Box<dyn Phase>),
+}
+
+/// What can the operator ask for in the middle of the game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    /// Pauses the match or resumes it if it's paused.
+    TogglePause,
+
+    /// Gives a point to given player.
+    Award(u8),
+
+    /// Takes back the last point.
+    Undo,
+
+    /// Starts the score of the match over.
+    ResetScore,
+
+    /// Gives given player the next handicap preset. Handicaps can only be
+    /// changed before a match.
+    Handicap(u8),
+}
+
+/// Returns how many seconds has the last frame taken.
+fn frame_time(ctx: &Context) -> f32 {
+    timer::duration_to_f64(timer::delta(ctx)) as f32
+}
+
+/// Throws away serves pressed while no ball is in play, so that the ball
+/// isn't served right after play resumes.
+fn ignore_serves(state: &mut Pong) {
+    for (_, controller) in state.controllers.iter_mut() {
+        controller.serves();
     }
 }
@@ -0,0 +1,55 @@
+use super::{ignore_serves, plays_pong, Command, Phase, PlaysPong, Transition};
+use crate::pong::Pong;
+use crate::scoreboard::{self, draw_facing};
+use crate::WINDOW_SIZE;
+use ggez::graphics::BLACK;
+use ggez::{Context, GameResult};
+
+/// How large is the text telling the players that the match is paused.
+const PAUSED_SIZE: f32 = 96.0;
+
+/// The match stands still until the operator resumes it.
+pub struct Paused;
+
+impl Phase for Paused {
+    fn enter(&mut self, state: &mut Pong) {
+        info!("Paused");
+        state.stop_robot();
+    }
+
+    /// Nothing moves.
+    fn update(
+        &mut self,
+        state: &mut Pong,
+        _ctx: &mut Context,
+    ) -> GameResult<Transition> {
+        ignore_serves(state);
+        Ok(Transition::Stay)
+    }
+
+    /// Draws the match as it stood when it was paused.
+    fn draw(&mut self, state: &mut Pong, ctx: &mut Context) -> GameResult<()> {
+        plays_pong::draw_match(state, ctx)?;
+
+        for player_id in 0..state.game.players {
+            let text = scoreboard::text("PAUSED".to_string(), PAUSED_SIZE);
+            let (width, _) = text.dimensions(ctx);
+            let position =
+                ((WINDOW_SIZE.0 - width as f32) / 2.0, WINDOW_SIZE.1 * 0.6);
+            draw_facing(ctx, &text, player_id, position, BLACK)?;
+        }
+
+        Ok(())
+    }
+
+    fn command(&mut self, state: &mut Pong, command: Command) -> Transition {
+        match command {
+            Command::TogglePause => Transition::To(Box::new(PlaysPong)),
+            _ => plays_pong::score(state, command),
+        }
+    }
+
+    fn exit(&mut self, _state: &mut Pong) {
+        info!("Resumed");
+    }
+}
@@ -1,28 +1,298 @@
-use super::super::Pong;
-use ggez::graphics::{DrawParam, Drawable};
+use super::{frame_time, Command, GameOver, Paused, Phase, Transition};
+use crate::paddle::Side;
+use crate::pong::Pong;
+use crate::powerups::{Effect, SHIELD_COLOR};
+use crate::scoreboard::{self, draw_facing};
+use crate::serve::Serve;
+use crate::simulation::{Inputs, Simulation};
+use crate::{DT, WINDOW_SIZE};
+use ggez::graphics::{
+    self, DrawParam, Drawable, Mesh, Scale, Text, TextFragment, BLACK,
+};
+use ggez::nalgebra::Point2;
 use ggez::{Context, GameResult};
+use std::mem;
 
-/// Updates the ball direction, velocity and position.
-pub fn update(state: &mut Pong) -> GameResult<()> {
-    if state.ball.player_scored().is_some() {
-        state.ball = Default::default();
+/// The longest frame the game catches up with. If a frame takes longer, e.g.
+/// while the window is being dragged, the game slows down instead of running
+/// many ticks at once.
+const MAX_FRAME_TIME: f32 = 0.25;
+
+/// How large are the digits of the count down before a serve.
+const COUNT_DOWN_SIZE: f32 = 96.0;
+
+/// How large are the labels of the effects in force.
+const EFFECT_SIZE: f32 = 24.0;
+
+/// How thick is the line of a shield along the player's edge.
+const SHIELD_THICKNESS: f32 = 4.0;
+
+/// The camera module is set up for both players and the match is played.
+pub struct PlaysPong;
+
+impl Phase for PlaysPong {
+    /// Runs as many ticks as fit into the time which passed since the last
+    /// frame. The rest of the time is carried over to the next frame. Once
+    /// the match is won, the game is over.
+    fn update(
+        &mut self,
+        state: &mut Pong,
+        ctx: &mut Context,
+    ) -> GameResult<Transition> {
+        state.lag += frame_time(ctx).min(MAX_FRAME_TIME);
+
+        while state.lag >= DT {
+            state.lag -= DT;
+            tick(state)?;
+            if state.game.is_over() {
+                return Ok(Transition::To(Box::new(GameOver::default())));
+            }
+        }
+
+        Ok(Transition::Stay)
+    }
+
+    fn draw(&mut self, state: &mut Pong, ctx: &mut Context) -> GameResult<()> {
+        draw_match(state, ctx)
+    }
+
+    fn command(&mut self, state: &mut Pong, command: Command) -> Transition {
+        match command {
+            Command::TogglePause => Transition::To(Box::new(Paused)),
+            _ => score(state, command),
+        }
     }
+}
 
-    for ref paddle in state.paddles.iter() {
-        state.ball.bounce_from_paddle(paddle, &mut state.rand);
+/// Changes the score of a match in play as the operator asks. If an awarded
+/// point wins the match, the game is over.
+pub fn score(state: &mut Pong, command: Command) -> Transition {
+    match command {
+        Command::Award(player_id) => {
+            state.award_point(player_id);
+            if state.game.is_over() {
+                return Transition::To(Box::new(GameOver::default()));
+            }
+        }
+        Command::Undo => {
+            state.undo_point();
+        }
+        Command::ResetScore => state.reset_score(),
+        Command::TogglePause | Command::Handicap(_) => (),
     }
 
-    // Moves the ball and bounces the ball from the wall if close enough.
-    state.ball.tick(&mut state.rand);
+    Transition::Stay
+}
+
+/// Asks the controllers, or the replay being played, where the paddles go
+/// and moves the game by one tick.
+fn tick(state: &mut Pong) -> GameResult<()> {
+    let simulation = &mut state.simulation;
+    let playback = state.playback.as_mut().and_then(Iterator::next);
+    let inputs = match playback {
+        // The replay makes the changes it has recorded instead.
+        Some(inputs) => {
+            state.changes.clear();
+            inputs
+        }
+        None => {
+            let mut targets = vec![None; simulation.paddles.len()];
+            // Each player minds the ball which is the most dangerous to
+            // them, seen as if they sat by the top or the bottom edge.
+            // Reversed controls see the playfield flipped, so that their
+            // paddle goes the other way.
+            for (player_id, target) in targets.iter_mut().enumerate() {
+                let paddle = &simulation.paddles[player_id];
+                let side = paddle.side;
+                let ball = simulation.threat(player_id as u8).seen_from(side);
+                let controller = &mut state.controllers[player_id].1;
+                *target = if simulation.reversed(player_id as u8) {
+                    controller
+                        .target(&ball.mirrored(side), &paddle.mirrored().seen())
+                        .map(|x| side.length() - x)
+                } else {
+                    controller.target(&ball, &paddle.seen())
+                };
+            }
 
+            // Only the serving team's wish to serve counts, the other
+            // players' presses are thrown away.
+            let server = simulation.serve.map(|serve| serve.server);
+            let mut serve = false;
+            for (player_id, controller) in
+                state.controllers.iter_mut().enumerate()
+            {
+                let serves = controller.1.serves();
+                let team = simulation.team(player_id as u8);
+                serve |= serves && server == Some(team);
+            }
+            let changes = mem::replace(&mut state.changes, Vec::new());
+            Inputs {
+                targets,
+                serve,
+                changes,
+            }
+        }
+    };
+
+    state.scoreboard.tick();
+    for player_id in simulation.step(&inputs) {
+        state.game.concede(player_id);
+        info!(
+            "Player {} let a goal in, {:?} in the set, {:?} in total",
+            player_id,
+            state.game.points,
+            simulation.conceded()
+        );
+        if state.game.is_duel() {
+            state.scoreboard.point(1 - player_id);
+        }
+    }
+    state.scoreboard.rally(simulation.rally_length());
+    if state.game.alone.is_some() {
+        state.game.set_score(simulation.score());
+        if simulation.is_cleared() {
+            state.game.win();
+        }
+    }
+    state.replay.inputs.push(inputs);
+    state.game.tick();
+
+    // Sends the robot where the ball is heading.
+    if let Some(robot) = state.robot.as_mut() {
+        let paddle = &simulation.paddles[robot.player_id as usize];
+        let ball = simulation.threat(robot.player_id);
+        if let Err(e) = robot.steer(ball, paddle) {
+            error!("Cannot steer the robot: {}", e);
+        }
+    }
+
+    state.knock_out();
     Ok(())
 }
 
-/// Redraws the game GUI elements: the two paddles and the ball.
-pub fn draw(state: &mut Pong, ctx: &mut Context) -> GameResult<()> {
-    state.ball.draw(ctx, DrawParam::default())?;
-    state.paddles[0].draw(ctx, DrawParam::default())?;
-    state.paddles[1].draw(ctx, DrawParam::default())?;
+/// Redraws the game GUI elements: the paddles, the balls and the bricks.
+/// Since the frame usually comes in between two ticks, the objects are drawn
+/// in between their previous and current position.
+pub fn draw_match(state: &Pong, ctx: &mut Context) -> GameResult<()> {
+    let alpha = state.lag / DT;
+
+    for (obstacle, center) in state.simulation.obstacles() {
+        obstacle.draw(ctx, center)?;
+    }
+
+    for brick in state.simulation.bricks.iter() {
+        brick.draw(ctx)?;
+    }
+
+    for ball in state.simulation.balls.iter() {
+        let (x, y) = ball.center;
+        let (previous_x, previous_y) = ball.previous_center;
+        let offset = Point2::new(
+            interpolate(previous_x, x, alpha) - x,
+            interpolate(previous_y, y, alpha) - y,
+        );
+        ball.draw(ctx, DrawParam::default().dest(offset))?;
+    }
+
+    for paddle in state.simulation.paddles.iter() {
+        if !state.simulation.is_playing(paddle.player_id) {
+            continue;
+        }
+        let along = interpolate(paddle.previous_x, paddle.x, alpha) - paddle.x;
+        let (x, y) = paddle.side.point(along, 0.0);
+        paddle.draw(ctx, DrawParam::default().dest(Point2::new(x, y)))?;
+    }
+
+    if let Some(serve) = state.simulation.serve {
+        draw_count_down(ctx, serve)?;
+    }
+
+    draw_powerups(ctx, &state.simulation)?;
+    state.scoreboard.draw(ctx, &state.game)
+}
+
+// Draws the power-ups waiting on the playfield, the shields along the edges
+// of the players who have one and the effects in force with their time left
+// next to each player's paddle.
+fn draw_powerups(ctx: &mut Context, simulation: &Simulation) -> GameResult<()> {
+    for item in &simulation.items {
+        item.draw(ctx)?;
+    }
+
+    // Players of the same team share their edge, hence their effects are
+    // listed together.
+    let players = simulation.paddles.len() as u8;
+    for team in (0..players).filter(|p| simulation.team(*p) == *p) {
+        let active: Vec<_> = simulation
+            .active
+            .iter()
+            .filter(|active| simulation.team(active.player_id) == team)
+            .collect();
+        if active.is_empty() {
+            continue;
+        }
+
+        if active.iter().any(|active| active.effect == Effect::Shield) {
+            let side = Side::of(team);
+            let across = if side.away() > 0.0 {
+                SHIELD_THICKNESS / 2.0
+            } else {
+                side.across(WINDOW_SIZE) - SHIELD_THICKNESS / 2.0
+            };
+            let (x, y) = side.point(0.0, across);
+            let (to_x, to_y) = side.point(side.length(), across);
+            let line = Mesh::new_line(
+                ctx,
+                &[Point2::new(x, y), Point2::new(to_x, to_y)],
+                SHIELD_THICKNESS,
+                SHIELD_COLOR,
+            )?;
+            graphics::draw(ctx, &line, DrawParam::default())?;
+        }
+
+        let labels: Vec<_> = active
+            .iter()
+            .map(|active| {
+                format!("{} {}", active.effect.label(), active.seconds_left())
+            })
+            .collect();
+        let text = scoreboard::text(labels.join("  "), EFFECT_SIZE);
+        let (_, height) = text.dimensions(ctx);
+        let position = (
+            EFFECT_SIZE,
+            WINDOW_SIZE.1 - EFFECT_SIZE * 2.0 - height as f32,
+        );
+        draw_facing(ctx, &text, team, position, BLACK)?;
+    }
 
     Ok(())
 }
+
+// Shows how many seconds are left until the serve above the ball, on the
+// side of the player who is going to get the ball.
+fn draw_count_down(ctx: &mut Context, serve: Serve) -> GameResult<()> {
+    let seconds = serve.seconds_left();
+    if seconds == 0 {
+        return Ok(());
+    }
+
+    let text = Text::new(
+        TextFragment::new(seconds.to_string())
+            .color(BLACK)
+            .scale(Scale::uniform(COUNT_DOWN_SIZE)),
+    );
+    let (width, height) = text.dimensions(ctx);
+    let side = Side::of(serve.server);
+    let (x, y) = side.point(0.0, -side.away() * side.across(WINDOW_SIZE) / 6.0);
+    let dest = Point2::new(
+        (WINDOW_SIZE.0 - width as f32) / 2.0 + x,
+        (WINDOW_SIZE.1 - height as f32) / 2.0 + y,
+    );
+    graphics::draw(ctx, &text, DrawParam::default().dest(dest))
+}
+
+// Linear interpolation between two values.
+fn interpolate(from: f32, to: f32, alpha: f32) -> f32 {
+    from + (to - from) * alpha
+}
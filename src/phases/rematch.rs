@@ -0,0 +1,65 @@
+use super::{game_over, Command, Phase, PlaysPong, Transition};
+use crate::pong::Pong;
+use crate::scoreboard::{self, draw_facing};
+use crate::WINDOW_SIZE;
+use ggez::graphics::BLACK;
+use ggez::{Context, GameResult};
+
+/// How large is the offer of a rematch.
+const OFFER_SIZE: f32 = 48.0;
+
+/// Waits for any of the players to serve, then starts a fresh match on the
+/// playfield mapped before.
+pub struct Rematch;
+
+impl Phase for Rematch {
+    fn update(
+        &mut self,
+        state: &mut Pong,
+        _ctx: &mut Context,
+    ) -> GameResult<Transition> {
+        let mut serves = false;
+        for (_, controller) in state.controllers.iter_mut() {
+            serves |= controller.serves();
+        }
+
+        if serves {
+            state.rematch();
+            Ok(Transition::To(Box::new(PlaysPong)))
+        } else {
+            Ok(Transition::Stay)
+        }
+    }
+
+    /// Keeps the result of the last match and offers each player a rematch.
+    fn draw(&mut self, state: &mut Pong, ctx: &mut Context) -> GameResult<()> {
+        game_over::draw_result(state, ctx)?;
+
+        let handicaps = state.handicap_labels();
+        for (player_id, handicap) in (0..state.game.players).zip(handicaps) {
+            let offer = if handicap.is_empty() {
+                "Serve for a rematch".to_string()
+            } else {
+                format!("Serve for a rematch ({})", handicap)
+            };
+            let text = scoreboard::text(offer, OFFER_SIZE);
+            let (width, _) = text.dimensions(ctx);
+            let position =
+                ((WINDOW_SIZE.0 - width as f32) / 2.0, WINDOW_SIZE.1 * 0.85);
+            draw_facing(ctx, &text, player_id, position, BLACK)?;
+        }
+
+        Ok(())
+    }
+
+    /// The players can change their handicaps before the rematch.
+    fn command(&mut self, state: &mut Pong, command: Command) -> Transition {
+        match command {
+            Command::Handicap(player_id) => {
+                state.switch_handicap(player_id);
+                Transition::Stay
+            }
+            _ => game_over::reopen(state, command),
+        }
+    }
+}
@@ -0,0 +1,853 @@
+use super::ball::{Ball, RANDOM_BOUNCE_BOUND};
+use super::level::{Obstacle, Shape};
+use super::paddle::{Paddle, Side};
+use super::solo::{Brick, BRICK_SIZE};
+use super::{DT, WINDOW_SIZE};
+use nalgebra::{Isometry2, Vector2};
+use ncollide2d::events::ContactEvent;
+use ncollide2d::query;
+use ncollide2d::shape::{self, Cuboid, ShapeHandle};
+use ncollide2d::world::CollisionGroups;
+use nphysics2d::algebra::Velocity2;
+use nphysics2d::material::{BasicMaterial, MaterialHandle};
+use nphysics2d::object::{
+    BodyHandle, BodyStatus, Collider, ColliderDesc, ColliderHandle,
+    RigidBodyDesc,
+};
+use nphysics2d::world::World;
+use std::env;
+
+/// How thick are the walls. They are outside of the window and thick enough
+/// for nothing to go through them.
+const WALL_THICKNESS: f32 = 100.0;
+
+/// How many times at most is a tick split at the moments the balls hit
+/// something, e.g. in a corner between a paddle and a wall. The last step
+/// takes the rest of the tick.
+const MAX_STEPS: usize = 16;
+
+/// How long is the step in which a ball bounces off what it has just reached,
+/// in seconds. The world bounces it at the start of the step, so that it's
+/// swept again with its new velocity right after.
+const BOUNCE_STEP: f32 = DT / 100.0;
+
+/// How close in pixels must a ball be to something to touch it.
+const CONTACT_DISTANCE: f32 = 0.001;
+
+/// Collision group of everything but the paddles. The paddles along each
+/// side have a group of their own, see `paddle_group`.
+const BODY_GROUP: usize = 0;
+
+/// What did the ball bounce off.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Contact {
+    Wall,
+
+    /// Paddle of the player with given id.
+    Paddle(u8),
+
+    /// Another ball.
+    Ball,
+
+    /// Obstacle of the level with given index.
+    Obstacle(usize),
+
+    /// Brick of the wall in breakout with given index. The ball hits a brick
+    /// once it has bounced off it, so that the brick can break right away.
+    Brick(usize),
+}
+
+/// How the ball behaves when it hits something.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Materials {
+    /// How much of its speed does the ball keep in a bounce, 1 is all of it.
+    pub restitution: f32,
+
+    /// How much does the ball grip the surface it hits.
+    pub friction: f32,
+
+    /// How much at most is the ball's direction randomly nudged in a bounce,
+    /// zero for no randomness.
+    pub noise: f32,
+}
+
+impl Default for Materials {
+    fn default() -> Self {
+        Self {
+            restitution: 1.0,
+            friction: 0.0,
+            noise: RANDOM_BOUNCE_BOUND,
+        }
+    }
+}
+
+impl Materials {
+    /// Reads the materials from `PONG_RESTITUTION`, `PONG_FRICTION` and
+    /// `PONG_BOUNCE_NOISE` env vars.
+    pub fn from_env() -> Self {
+        let read = |var: &str, default: f32| {
+            env::var(var)
+                .map(|value| value.parse().expect("Invalid material"))
+                .unwrap_or(default)
+        };
+
+        let default = Self::default();
+        Self {
+            restitution: read("PONG_RESTITUTION", default.restitution),
+            friction: read("PONG_FRICTION", default.friction),
+            noise: read("PONG_BOUNCE_NOISE", default.noise),
+        }
+    }
+}
+
+/// The playfield as a physics world. The balls are rigid bodies, the paddles
+/// are kinematic bodies which go wherever their players move them, and so are
+/// the obstacles, which go wherever the level moves them. The walls are static
+/// colliders.
+pub struct Physics {
+    world: World<f32>,
+
+    // What the balls bounce off.
+    material: MaterialHandle<f32>,
+
+    // Bodies of the balls in the same order as the balls of the game.
+    balls: Vec<BodyHandle>,
+
+    // Bodies of the paddles of the players whose goals are open.
+    paddles: Vec<Option<BodyHandle>>,
+
+    // Walls along each side in the order of `Side::ALL`, if there's no
+    // goal there.
+    walls: [Option<ColliderHandle>; 4],
+
+    // How wide is each paddle's body, so that it's rebuilt when the paddle
+    // changes its width.
+    widths: Vec<f32>,
+
+    // Bodies of the obstacles in the same order as in the level.
+    obstacles: Vec<BodyHandle>,
+
+    // Colliders of the bricks in the same order as the bricks of the game.
+    bricks: Vec<ColliderHandle>,
+}
+
+impl Physics {
+    /// Creates the playfield with the paddles, the obstacles and the walls
+    /// along the sides without paddles, but no balls.
+    pub fn new(
+        materials: Materials,
+        paddles: &[Paddle],
+        obstacles: &[Obstacle],
+    ) -> Self {
+        let mut world = World::new();
+        world.set_gravity(Vector2::zeros());
+        let material = MaterialHandle::new(BasicMaterial::new(
+            materials.restitution,
+            materials.friction,
+        ));
+
+        let mut physics = Self {
+            world,
+            material,
+            balls: Vec::new(),
+            paddles: Vec::new(),
+            walls: [None; 4],
+            widths: Vec::new(),
+            obstacles: Vec::new(),
+            bricks: Vec::new(),
+        };
+        for paddle in paddles {
+            let handle = physics.paddle_body(paddle);
+            physics.paddles.push(Some(handle));
+            physics.widths.push(paddle.width);
+        }
+        for side in Side::ALL.iter() {
+            if paddles.iter().all(|paddle| paddle.side != *side) {
+                physics.build_wall(*side);
+            }
+        }
+        for obstacle in obstacles {
+            let shape = match obstacle.shape {
+                Shape::Rectangle { width, height } => ShapeHandle::new(
+                    Cuboid::new(Vector2::new(width / 2.0, height / 2.0)),
+                ),
+                Shape::Circle { radius } => {
+                    ShapeHandle::new(shape::Ball::new(radius))
+                }
+            };
+            let (x, y) = obstacle.center_at(0);
+            let handle = RigidBodyDesc::new()
+                .status(BodyStatus::Kinematic)
+                .translation(Vector2::new(x, y))
+                .sleep_threshold(None)
+                .collider(
+                    &ColliderDesc::new(shape)
+                        .material(physics.material.clone())
+                        .collision_groups(body_groups()),
+                )
+                .build(&mut physics.world)
+                .handle();
+            physics.obstacles.push(handle);
+        }
+
+        physics
+    }
+
+    /// Takes the paddle with given index out and puts a wall in front of its
+    /// goal, e.g. when its player has been knocked out.
+    pub fn close_goal(&mut self, index: usize, side: Side) {
+        if let Some(handle) = self.paddles[index].take() {
+            self.world.remove_bodies(&[handle]);
+        }
+        self.build_wall(side);
+    }
+
+    /// Takes the wall in front of the goal of given paddle away and puts the
+    /// paddle back at given index.
+    pub fn open_goal(&mut self, index: usize, paddle: &Paddle) {
+        let wall = &mut self.walls[usize::from(paddle.side.player_id())];
+        if let Some(handle) = wall.take() {
+            self.world.remove_colliders(&[handle]);
+        }
+        if self.paddles[index].is_none() {
+            self.paddles[index] = Some(self.paddle_body(paddle));
+            self.widths[index] = paddle.width;
+        }
+    }
+
+    // Puts a wall along given side of the window, outside of it and long
+    // enough to cover also the corners.
+    fn build_wall(&mut self, side: Side) {
+        let wall = &mut self.walls[usize::from(side.player_id())];
+        if wall.is_some() {
+            return;
+        }
+
+        let (x, y) = side.point(side.length(), WALL_THICKNESS / 2.0);
+        let shape = ShapeHandle::new(Cuboid::new(Vector2::new(x, y)));
+        let across = if side.away() > 0.0 {
+            -WALL_THICKNESS / 2.0
+        } else {
+            side.across(WINDOW_SIZE) + WALL_THICKNESS / 2.0
+        };
+        let (x, y) = side.point(side.length() / 2.0, across);
+        let handle = ColliderDesc::new(shape)
+            .translation(Vector2::new(x, y))
+            .material(self.material.clone())
+            .collision_groups(body_groups())
+            .build(&mut self.world)
+            .handle();
+        *wall = Some(handle);
+    }
+
+    // Builds the kinematic body of given paddle.
+    fn paddle_body(&mut self, paddle: &Paddle) -> BodyHandle {
+        let (width, height) = paddle.size();
+        let shape = ShapeHandle::new(Cuboid::new(Vector2::new(
+            width / 2.0,
+            height / 2.0,
+        )));
+        RigidBodyDesc::new()
+            .status(BodyStatus::Kinematic)
+            .translation(paddle_center(paddle, paddle.x))
+            .sleep_threshold(None)
+            .collider(
+                &ColliderDesc::new(shape)
+                    .material(self.material.clone())
+                    .collision_groups(
+                        CollisionGroups::new()
+                            .with_membership(&[paddle_group(paddle.side)]),
+                    ),
+            )
+            .build(&mut self.world)
+            .handle()
+    }
+
+    /// Puts a brick into the playfield after all the others. Bricks are
+    /// static colliders just like the walls.
+    pub fn add_brick(&mut self, brick: &Brick) {
+        let (width, height) = BRICK_SIZE;
+        let shape =
+            ShapeHandle::new(Cuboid::new(Vector2::new(width, height) / 2.0));
+        let (x, y) = brick.center;
+        let handle = ColliderDesc::new(shape)
+            .translation(Vector2::new(x, y))
+            .material(self.material.clone())
+            .collision_groups(body_groups())
+            .build(&mut self.world)
+            .handle();
+        self.bricks.push(handle);
+    }
+
+    /// Takes the brick at given index out of the playfield.
+    pub fn remove_brick(&mut self, index: usize) {
+        let handle = self.bricks.remove(index);
+        self.world.remove_colliders(&[handle]);
+    }
+
+    /// Puts a new ball into the playfield after all the others.
+    pub fn add_ball(&mut self, ball: &Ball) {
+        let handle = RigidBodyDesc::new()
+            .translation(Vector2::new(ball.center.0, ball.center.1))
+            .sleep_threshold(None)
+            .collider(
+                &ColliderDesc::new(ShapeHandle::new(shape::Ball::new(
+                    ball.radius,
+                )))
+                .density(1.0)
+                .material(self.material.clone())
+                .collision_groups(body_groups()),
+            )
+            .build(&mut self.world)
+            .handle();
+        self.balls.push(handle);
+    }
+
+    /// Takes the ball at given index out of the playfield.
+    pub fn remove_ball(&mut self, index: usize) {
+        let handle = self.balls.remove(index);
+        self.world.remove_bodies(&[handle]);
+    }
+
+    /// Moves the balls, the paddles and the obstacles by one tick. The
+    /// paddles go from their previous position to the current one, the
+    /// obstacles from where they are after given number of ticks of play to
+    /// where they are after the next one and the balls go from where they are
+    /// in the direction they are heading. A paddle which has changed its
+    /// width gets a new body. Returns which ball has hit what, by the index
+    /// of the ball.
+    pub fn step(
+        &mut self,
+        balls: &mut [Ball],
+        paddles: &[Paddle],
+        obstacles: &[Obstacle],
+        ticks: u32,
+    ) -> Vec<(usize, Contact)> {
+        for (index, paddle) in paddles.iter().enumerate() {
+            let resized =
+                (self.widths[index] - paddle.width).abs() > std::f32::EPSILON;
+            if let (true, Some(handle)) = (resized, self.paddles[index]) {
+                self.world.remove_bodies(&[handle]);
+                self.paddles[index] = Some(self.paddle_body(paddle));
+                self.widths[index] = paddle.width;
+            }
+        }
+
+        for (handle, paddle) in self.paddles.iter().zip(paddles) {
+            let handle = match handle {
+                Some(handle) => *handle,
+                None => continue,
+            };
+            let body = self
+                .world
+                .rigid_body_mut(handle)
+                .expect("Cannot find paddle body");
+            body.set_position(Isometry2::new(
+                paddle_center(paddle, paddle.previous_x),
+                0.0,
+            ));
+            let (x, y) =
+                paddle.side.point((paddle.x - paddle.previous_x) / DT, 0.0);
+            body.set_linear_velocity(Vector2::new(x, y));
+        }
+
+        for (handle, obstacle) in self.obstacles.iter().zip(obstacles) {
+            let (x, y) = obstacle.center_at(ticks);
+            let (next_x, next_y) = obstacle.center_at(ticks + 1);
+            let body = self
+                .world
+                .rigid_body_mut(*handle)
+                .expect("Cannot find obstacle body");
+            body.set_position(Isometry2::new(Vector2::new(x, y), 0.0));
+            body.set_linear_velocity(Vector2::new(
+                (next_x - x) / DT,
+                (next_y - y) / DT,
+            ));
+        }
+
+        self.place_balls(balls);
+
+        // Splits the tick at the moments the balls hit something, so that
+        // they bounce off at the point of impact instead of tunnelling
+        // through.
+        let mut contacts = Vec::new();
+        let mut left = DT;
+        for step in 0..MAX_STEPS {
+            let handles = self.balls.clone();
+            for handle in handles {
+                let velocity = self.linear_velocity(handle);
+                self.ignore_paddles_behind(handle, (velocity.x, velocity.y));
+            }
+
+            let impact = (0..self.balls.len())
+                .filter_map(|index| self.sweep(index, left))
+                .fold(None, |soonest: Option<f32>, time| {
+                    Some(soonest.map_or(time, |soonest| soonest.min(time)))
+                });
+            let time = match impact {
+                _ if step + 1 == MAX_STEPS => left,
+                Some(time) if time > 0.0 => time,
+                Some(_) => BOUNCE_STEP.min(left),
+                None => left,
+            };
+            self.world.set_timestep(time);
+            self.world.step();
+            left -= time;
+            for event in self.world.contact_events().iter() {
+                match *event {
+                    ContactEvent::Started(a, b) => {
+                        contacts.extend(self.contact(a, b));
+                        contacts.extend(self.contact(b, a));
+                    }
+                    ContactEvent::Stopped(a, b) => {
+                        contacts.extend(self.left_brick(a, b));
+                        contacts.extend(self.left_brick(b, a));
+                    }
+                }
+            }
+            if left <= 0.0 {
+                break;
+            }
+        }
+
+        for (handle, ball) in self.balls.iter().zip(balls.iter_mut()) {
+            let body = self
+                .world
+                .rigid_body(*handle)
+                .expect("Cannot find ball body");
+            let position = body.position().translation.vector;
+            let Velocity2 { linear, .. } = *body.velocity();
+            ball.follow((position.x, position.y), (linear.x, linear.y));
+        }
+
+        contacts
+    }
+
+    // Puts the bodies of the balls where the balls are and sets them going
+    // the way the balls are.
+    fn place_balls(&mut self, balls: &[Ball]) {
+        for (handle, ball) in self.balls.iter().zip(balls) {
+            let (vx, vy) = ball.velocity();
+            let body = self
+                .world
+                .rigid_body_mut(*handle)
+                .expect("Cannot find ball body");
+            body.set_position(Isometry2::new(
+                Vector2::new(ball.center.0, ball.center.1),
+                0.0,
+            ));
+            body.set_linear_velocity(Vector2::new(vx, vy));
+        }
+    }
+
+    // Sweeps the ball at given index along its path and returns in how many
+    // seconds it hits the first thing in its way, if it does within given
+    // time. The paddles and the obstacles move along while it does. A ball
+    // which already touches something it's heading at hits it right away,
+    // one moving away from it doesn't hit it at all.
+    fn sweep(&self, index: usize, time: f32) -> Option<f32> {
+        let colliders = self.world.collider_world();
+        let handle = self.balls[index];
+        let ball = colliders.body_colliders(handle).next()?;
+        let position = self.collider_position(ball);
+        let velocity = self.linear_velocity(handle);
+
+        let mut soonest: Option<f32> = None;
+        for other in colliders.colliders() {
+            let groups = ball.collision_groups();
+            if other.handle() == ball.handle()
+                || !groups.can_interact_with_groups(other.collision_groups())
+            {
+                continue;
+            }
+
+            let path = velocity - self.linear_velocity(other.body());
+            let other_position = self.collider_position(other);
+            let mut impact = match query::time_of_impact(
+                &position,
+                &path,
+                ball.shape().as_ref(),
+                &other_position,
+                &Vector2::zeros(),
+                other.shape().as_ref(),
+            ) {
+                Some(impact) if impact <= time => impact,
+                _ => continue,
+            };
+            if impact * path.norm() < CONTACT_DISTANCE {
+                let contact = query::contact(
+                    &position,
+                    ball.shape().as_ref(),
+                    &other_position,
+                    other.shape().as_ref(),
+                    CONTACT_DISTANCE,
+                );
+                let heading = contact
+                    .map_or(false, |contact| contact.normal.dot(&path) > 0.0);
+                if !heading {
+                    continue;
+                }
+                impact = 0.0;
+            }
+            soonest =
+                Some(soonest.map_or(impact, |soonest| soonest.min(impact)));
+        }
+
+        soonest
+    }
+
+    // Returns where given collider is, also when its body has moved since
+    // the world last synced the colliders with the bodies.
+    fn collider_position(&self, collider: &Collider<f32>) -> Isometry2<f32> {
+        match self.world.rigid_body(collider.body()) {
+            Some(body) => body.position() * collider.position_wrt_body(),
+            None => *collider.position(),
+        }
+    }
+
+    // Returns the velocity of given body, zero for the static colliders.
+    fn linear_velocity(&self, handle: BodyHandle) -> Vector2<f32> {
+        self.world
+            .rigid_body(handle)
+            .map_or_else(Vector2::zeros, |body| body.velocity().linear)
+    }
+
+    // Lets the ball with given body pass through the paddles along the sides
+    // it is heading away from. A ball only bounces off the paddles it is
+    // coming at, hence it passes the front paddle of a doubles team on its
+    // way from the back one.
+    fn ignore_paddles_behind(
+        &mut self,
+        handle: BodyHandle,
+        velocity: (f32, f32),
+    ) {
+        let behind: Vec<_> = Side::ALL
+            .iter()
+            .filter(|side| side.across(velocity) * side.away() > 0.0)
+            .map(|side| paddle_group(*side))
+            .collect();
+
+        let colliders = self.world.collider_world();
+        let collider = match colliders.body_colliders(handle).next() {
+            Some(collider) => collider,
+            None => return,
+        };
+        let groups = collider.collision_groups();
+        let changed = Side::ALL.iter().any(|side| {
+            let group = paddle_group(*side);
+            groups.is_group_blacklisted(group) != behind.contains(&group)
+        });
+        if changed {
+            let collider = collider.handle();
+            self.world.collider_world_mut().set_collision_groups(
+                collider,
+                body_groups().with_blacklist(&behind),
+            );
+        }
+    }
+
+    // Returns which ball has hit what if the first collider is a ball.
+    fn contact(
+        &self,
+        ball: ColliderHandle,
+        collider: ColliderHandle,
+    ) -> Option<(usize, Contact)> {
+        let ball = self.world.collider_body_handle(ball)?;
+        let other = self.world.collider_body_handle(collider)?;
+        let index = self.balls.iter().position(|handle| *handle == ball)?;
+
+        let paddle = self
+            .paddles
+            .iter()
+            .position(|paddle| *paddle == Some(other));
+        let obstacle = self.obstacles.iter().position(|body| *body == other);
+        let brick = self.bricks.iter().position(|brick| *brick == collider);
+        let contact = if self.balls.contains(&other) {
+            Contact::Ball
+        } else if let Some(player_id) = paddle {
+            Contact::Paddle(player_id as u8)
+        } else if let Some(index) = obstacle {
+            Contact::Obstacle(index)
+        } else if brick.is_some() {
+            return None;
+        } else {
+            Contact::Wall
+        };
+
+        Some((index, contact))
+    }
+
+    // Returns which ball has bounced off which brick if the first collider
+    // is a ball and the other one a brick.
+    fn left_brick(
+        &self,
+        ball: ColliderHandle,
+        brick: ColliderHandle,
+    ) -> Option<(usize, Contact)> {
+        let ball = self.world.collider_body_handle(ball)?;
+        let index = self.balls.iter().position(|handle| *handle == ball)?;
+        let brick = self.bricks.iter().position(|handle| *handle == brick)?;
+        Some((index, Contact::Brick(brick)))
+    }
+}
+
+// Returns the collision groups of a wall, an obstacle or a ball.
+fn body_groups() -> CollisionGroups {
+    CollisionGroups::new().with_membership(&[BODY_GROUP])
+}
+
+// Returns the collision group of the paddles along given side.
+fn paddle_group(side: Side) -> usize {
+    BODY_GROUP + 1 + usize::from(side.player_id())
+}
+
+// Returns the center of the paddle if it was at given coordinate along its
+// edge.
+fn paddle_center(paddle: &Paddle, x: f32) -> Vector2<f32> {
+    let side = paddle.side;
+    let across = side.across(paddle.position()) + paddle.height / 2.0;
+    let (x, y) = side.point(x, across);
+    Vector2::new(x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paddle::Track;
+
+    #[test]
+    fn test_fast_ball_does_not_pass_through_paddle() {
+        let paddles = [Paddle::new(1)];
+        let line = paddles[0].ball_line(13.0);
+        let mut ball = Ball::default();
+        ball.follow((600.0, line - 10.0), (0.0, 50.0 / DT));
+        let mut physics = Physics::new(Materials::default(), &paddles, &[]);
+        physics.add_ball(&ball);
+        let mut balls = [ball];
+
+        // The ball would travel right through the paddle within the tick.
+        let contacts = physics.step(&mut balls, &paddles, &[], 0);
+        assert_eq!(vec![(0, Contact::Paddle(0))], contacts);
+        assert!(balls[0].direction().1 < 0.0);
+        assert!(balls[0].center.1 < line);
+
+        // Not even when it goes several times its size in one tick.
+        balls[0].follow((600.0, line - 10.0), (0.0, 200.0 / DT));
+        let contacts = physics.step(&mut balls, &paddles, &[], 0);
+        assert_eq!(vec![(0, Contact::Paddle(0))], contacts);
+        assert!(balls[0].center.1 < line);
+    }
+
+    #[test]
+    fn test_sweeps_paddle_face() {
+        let paddles = [Paddle::new(1)];
+        let line = paddles[0].ball_line(13.0);
+        let mut physics = Physics::new(Materials::default(), &paddles, &[]);
+        let mut ball = Ball::default();
+
+        // Starts 10px before the face and moves 40px, right through the
+        // paddle.
+        ball.follow((600.0, line - 10.0), (0.0, 40.0 / DT));
+        physics.add_ball(&ball);
+        let mut balls = [ball];
+        physics.place_balls(&balls);
+        let time = physics.sweep(0, DT).unwrap();
+        assert!((time - 0.25 * DT).abs() < 0.01 * DT);
+
+        // Moving away from the face.
+        balls[0].follow((600.0, line), (0.0, -40.0 / DT));
+        physics.place_balls(&balls);
+        assert_eq!(None, physics.sweep(0, DT));
+
+        // Stops short of the face.
+        balls[0].follow((600.0, line - 50.0), (0.0, 40.0 / DT));
+        physics.place_balls(&balls);
+        assert_eq!(None, physics.sweep(0, DT));
+    }
+
+    #[test]
+    fn test_bounces_off_paddle_corner() {
+        let paddles = [Paddle::new(1)];
+        let (x, y) = paddles[0].position();
+        let mut ball = Ball::default();
+        ball.follow((x - 20.0, y - 20.0), (20.0 / DT, 20.0 / DT));
+        let mut physics = Physics::new(Materials::default(), &paddles, &[]);
+        physics.add_ball(&ball);
+        let mut balls = [ball];
+
+        let contacts = physics.step(&mut balls, &paddles, &[], 0);
+        assert_eq!(vec![(0, Contact::Paddle(0))], contacts);
+        let (dx, dy) = balls[0].direction();
+        assert!((dx + 1.0).abs() < 0.01);
+        assert!((dy + 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_sweeps_walls() {
+        let mut physics = Physics::new(Materials::default(), &[], &[]);
+        let mut ball = Ball::default();
+        ball.follow((WINDOW_SIZE.0 - 23.0, 300.0), (20.0 / DT, 5.0 / DT));
+        physics.add_ball(&ball);
+        let mut balls = [ball];
+        physics.place_balls(&balls);
+        let time = physics.sweep(0, DT).unwrap();
+        assert!((time - 0.5 * DT).abs() < 0.01 * DT);
+
+        balls[0].follow((23.0, 300.0), (20.0 / DT, 5.0 / DT));
+        physics.place_balls(&balls);
+        assert_eq!(None, physics.sweep(0, DT));
+
+        // The ball got past the wall, it's still sent back right away.
+        balls[0].follow((WINDOW_SIZE.0 + 5.0, 300.0), (20.0 / DT, 5.0 / DT));
+        physics.place_balls(&balls);
+        assert_eq!(Some(0.0), physics.sweep(0, DT));
+    }
+
+    #[test]
+    fn test_ball_passes_paddle_from_behind() {
+        let track = Track {
+            offset: 200.0,
+            ..Default::default()
+        };
+        let paddles = [Paddle::new(0).with_track(track)];
+        let line = paddles[0].ball_line(13.0);
+        let mut physics = Physics::new(Materials::default(), &paddles, &[]);
+
+        // The ball comes from the back of the paddle and goes right through.
+        let mut ball = Ball::default();
+        ball.follow((600.0, line - 40.0), (0.0, 30.0 / DT));
+        physics.add_ball(&ball);
+        let mut balls = [ball.clone()];
+        for _ in 0..3 {
+            assert!(physics.step(&mut balls, &paddles, &[], 0).is_empty());
+        }
+        assert!(balls[0].center.1 > line);
+
+        // On its way back it bounces off the face of the paddle.
+        balls[0].follow((600.0, line + 10.0), (0.0, -20.0 / DT));
+        let contacts = physics.step(&mut balls, &paddles, &[], 0);
+        assert_eq!(vec![(0, Contact::Paddle(0))], contacts);
+        assert!(balls[0].direction().1 > 0.0);
+    }
+
+    #[test]
+    fn test_bounces_off_wall() {
+        let mut ball = Ball::default();
+        ball.follow((WINDOW_SIZE.0 - 23.0, 300.0), (40.0 / DT, 0.0));
+        let mut physics = Physics::new(Materials::default(), &[], &[]);
+        physics.add_ball(&ball);
+        let mut balls = [ball];
+
+        let contacts = physics.step(&mut balls, &[], &[], 0);
+        assert_eq!(vec![(0, Contact::Wall)], contacts);
+        assert!(balls[0].direction().0 < 0.0);
+        assert!((balls[0].center.0 - (WINDOW_SIZE.0 - 43.0)).abs() < 3.0);
+    }
+
+    #[test]
+    fn test_restitution_slows_down_ball() {
+        let materials = Materials {
+            restitution: 0.5,
+            ..Default::default()
+        };
+        let mut ball = Ball::default();
+        ball.follow((WINDOW_SIZE.0 - 23.0, 300.0), (20.0 / DT, 0.0));
+        let mut physics = Physics::new(materials, &[], &[]);
+        physics.add_ball(&ball);
+        let mut balls = [ball];
+
+        physics.step(&mut balls, &[], &[], 0);
+        let (vx, _) = balls[0].velocity();
+        assert!((vx + 10.0 / DT).abs() < 30.0);
+    }
+
+    #[test]
+    fn test_balls_bounce_off_each_other() {
+        let mut a = Ball::default();
+        a.follow((500.0, 300.0), (20.0 / DT, 0.0));
+        let mut b = Ball::default();
+        b.follow((540.0, 300.0), (-20.0 / DT, 0.0));
+        let mut physics = Physics::new(Materials::default(), &[], &[]);
+        physics.add_ball(&a);
+        physics.add_ball(&b);
+        let mut balls = [a, b];
+
+        let contacts = physics.step(&mut balls, &[], &[], 0);
+        assert!(contacts.contains(&(0, Contact::Ball)));
+        assert!(contacts.contains(&(1, Contact::Ball)));
+        assert!(balls[0].direction().0 < 0.0);
+        assert!(balls[1].direction().0 > 0.0);
+
+        // A removed ball doesn't collide any more.
+        physics.remove_ball(0);
+        let mut balls = [balls[1].clone()];
+        balls[0].follow((500.0, 300.0), (20.0 / DT, 0.0));
+        assert!(physics.step(&mut balls, &[], &[], 0).is_empty());
+    }
+
+    #[test]
+    fn test_paddle_changes_width() {
+        let mut paddles = [Paddle::new(1)];
+        let line = paddles[0].ball_line(13.0);
+        let mut physics = Physics::new(Materials::default(), &paddles, &[]);
+
+        // The ball falls just past the end of the paddle.
+        let x = paddles[0].x + paddles[0].width / 2.0 + 20.0;
+        let mut ball = Ball::default();
+        ball.follow((x, line - 10.0), (0.0, 20.0 / DT));
+        physics.add_ball(&ball);
+        let mut balls = [ball.clone()];
+        assert!(physics.step(&mut balls, &paddles, &[], 0).is_empty());
+
+        // A wider paddle catches it.
+        paddles[0].width *= 1.5;
+        let mut balls = [ball];
+        let contacts = physics.step(&mut balls, &paddles, &[], 0);
+        assert_eq!(vec![(0, Contact::Paddle(0))], contacts);
+    }
+
+    #[test]
+    fn test_bounces_off_obstacles() {
+        let obstacles = [
+            Obstacle {
+                shape: Shape::Circle { radius: 40.0 },
+                center: (300.0, 300.0),
+                moves_to: None,
+                period: 1.0,
+                bumper: 0.0,
+            },
+            Obstacle {
+                shape: Shape::Rectangle {
+                    width: 200.0,
+                    height: 20.0,
+                },
+                center: (800.0, 300.0),
+                moves_to: Some((800.0, 500.0)),
+                period: 1.0,
+                bumper: 0.0,
+            },
+        ];
+        let mut physics = Physics::new(Materials::default(), &[], &obstacles);
+        let mut a = Ball::default();
+        a.follow((300.0, 240.0), (0.0, 20.0 / DT));
+        physics.add_ball(&a);
+
+        // The rectangle is out of the ball's reach at first.
+        let mut b = Ball::default();
+        b.follow((800.0, 340.0), (0.0, -5.0 / DT));
+        physics.add_ball(&b);
+        let mut balls = [a, b];
+
+        let contacts = physics.step(&mut balls, &[], &obstacles, 0);
+        assert_eq!(vec![(0, Contact::Obstacle(0))], contacts);
+        assert!(balls[0].direction().1 < 0.0);
+        assert!(balls[1].direction().1 < 0.0);
+
+        // Three quarters of a second in, it's half way back up and going
+        // fast, so that it hits the ball above it.
+        balls[1].follow((800.0, 374.0), (0.0, 1.0 / DT));
+        let contacts = physics.step(&mut balls, &[], &obstacles, 45);
+        assert!(contacts.contains(&(1, Contact::Obstacle(1))));
+        assert!(balls[1].direction().1 < 0.0);
+    }
+}
@@ -1,61 +1,434 @@
-use super::ball::Ball;
-use super::camera::Camera;
-use super::paddle::Paddle;
-use super::phases::Phase;
-use ggez::event::EventHandler;
+use super::camera::{self, Camera, Tracking};
+use super::controllers::{self, Controller, Event, Kind};
+use super::paddle::Side;
+use super::phases::{Command, MapsPlayfield, Phase, Transition};
+use super::robot::Robot;
+use super::rules::{Match, Rules};
+use super::scoreboard::Scoreboard;
+use super::simulation::{Change, Inputs, Replay, Settings, Simulation};
+use super::solo::SOLO_PLAYER;
+use ggez::event::{
+    self, Axis, Button, EventHandler, KeyCode, KeyMods, MouseButton,
+};
+use ggez::input::gamepad::GamepadId;
 use ggez::{graphics, Context, GameResult};
-use rand::rngs::ThreadRng;
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::vec;
 
 /// Game state that glues all parts of the game together.
 pub struct Pong {
-    /// Player's paddles.
-    pub paddles: [Paddle; 2],
+    /// The ball and the paddles.
+    pub simulation: Simulation,
 
-    /// Which phase is the game currently in. This is useful for view switching.
-    pub phase: Phase,
+    /// What moves each player's paddle and which kind of controller it is.
+    pub controllers: Vec<(Kind, Box<dyn Controller>)>,
+
+    /// Which phase is the game currently in. The phase is taken out while
+    /// it runs, so that it can change the rest of the state.
+    phase: Option<Box<dyn Phase>>,
 
     /// Input interface. We only need the camera during the set up phase, when
     /// the game starts we can disown camera object to go do work in its own
-    /// thread.
+    /// thread. There's no camera if no player needs it.
     pub camera: Option<Camera>,
 
-    /// First float represents balls x coordinate, second float balls
-    pub ball: Ball,
+    /// Positions which the camera thread updates, if there's a camera.
+    pub camera_positions: Option<Vec<Arc<Mutex<u32>>>>,
+
+    /// Asks the camera thread to map the playfield again, if there's a
+    /// camera.
+    pub camera_remap: Option<Arc<AtomicBool>>,
+
+    /// Robot acting as one of the paddles, if there's one connected.
+    pub robot: Option<Robot>,
+
+    /// Score of the match being played.
+    pub game: Match,
+
+    /// Shows the score to the players.
+    pub scoreboard: Scoreboard,
+
+    /// Inputs of every tick of the match played so far, saved into
+    /// `PONG_RECORD` file when the match or the game ends.
+    pub replay: Replay,
+
+    /// Inputs of a replay which is being played instead of the controllers.
+    /// When it runs out, the controllers take over.
+    pub playback: Option<vec::IntoIter<Inputs>>,
 
-    /// Thread for rand create to generate random numbers.
-    pub rand: ThreadRng,
+    /// Changes to the match since the last tick, which the next tick makes
+    /// and records into the replay.
+    pub changes: Vec<Change>,
+
+    /// How many seconds have passed since the last tick. The game catches up
+    /// in ticks of fixed duration.
+    pub lag: f32,
+
+    /// How every match is set up, kept for rematches.
+    settings: Settings,
+
+    /// Gamepads in the order they were first used. The index is the number
+    /// the controllers know the gamepad by.
+    gamepads: Vec<GamepadId>,
 }
 
 impl Pong {
     /// Creates a new state object.
     pub fn new(_: &mut Context) -> Self {
-        let camera = Camera::new();
+        let settings = Settings::from_env();
+        let players = settings.players;
+        let mut kinds: Vec<_> = (0..players).map(Kind::from_env).collect();
+        let sides: Vec<_> = (0..players)
+            .map(|player_id| Side::of(settings.team(player_id)))
+            .collect();
+        // Players of a doubles team share their half, the back player
+        // plays by the edge and the front player in front of them.
+        let zones = camera::zones(&sides)
+            .into_iter()
+            .enumerate()
+            .map(|(player_id, zone)| {
+                if !settings.doubles {
+                    return zone;
+                }
+                let (back, front) = zone.split();
+                let player_id = player_id as u8;
+                if settings.team(player_id) == player_id {
+                    back
+                } else {
+                    front
+                }
+            })
+            .collect();
+        let tracking = Tracking::from_env();
 
-        Pong {
-            paddles: [
-                Paddle::new(0, camera.positions[0].clone()),
-                Paddle::new(1, camera.positions[1].clone()),
-            ],
-            camera: Some(camera),
-            ball: Default::default(),
-            // Count downs 3 times one second before taking a picture of the
-            // playfield.
-            phase: Phase::MapsPlayfield { count_down: 3 },
-            rand: ThreadRng::default(),
+        // The camera is only needed if it tracks a player's hand or markers.
+        // If it's not available, the players play with the keyboard instead.
+        let camera = match tracking {
+            Tracking::Columns if !kinds.contains(&Kind::Camera) => None,
+            _ => match Camera::new(tracking.clone(), zones) {
+                Ok(camera) => Some(camera),
+                Err(e) => {
+                    warn!("Cannot open camera, using keyboard instead: {}", e);
+                    for kind in kinds.iter_mut() {
+                        if *kind == Kind::Camera {
+                            *kind = Kind::Keyboard;
+                        }
+                    }
+                    None
+                }
+            },
+        };
+        let camera_positions = camera.as_ref().map(|c| c.positions.clone());
+        let camera_remap = camera.as_ref().map(|c| c.remap.clone());
+        let robot = camera
+            .as_ref()
+            .and_then(|c| Robot::from_env(&tracking, c.markers.clone()));
+
+        let controller = |player_id: u8| {
+            let kind = kinds[player_id as usize];
+            info!("Player {} plays with {:?}", player_id, kind);
+            let controller = controllers::new(
+                kind,
+                player_id,
+                sides[player_id as usize],
+                camera_positions.as_ref().map(Vec::as_slice),
+            )
+            .expect("Cannot create controller");
+            (kind, controller)
+        };
+
+        // A replay is played with its own seed. Otherwise the seed can be
+        // fixed to play the same match again.
+        let playback = env::var("PONG_REPLAY").ok().map(|path| {
+            Replay::load(path, &settings).expect("Cannot load replay")
+        });
+        let seed = match &playback {
+            Some(replay) => replay.seed,
+            None => seed_from_env(),
+        };
+        info!("Playing with seed {}", seed);
+
+        let mut pong = Pong {
+            simulation: Simulation::new(seed, settings.clone()),
+            controllers: (0..players).map(controller).collect(),
+            camera,
+            camera_positions,
+            camera_remap,
+            robot,
+            phase: None,
+            game: new_match(&settings, Rules::from_env()),
+            scoreboard: Scoreboard::default(),
+            replay: Replay::new(seed, settings.clone()),
+            playback: playback.map(|replay| replay.inputs.into_iter()),
+            changes: Vec::new(),
+            lag: 0.0,
+            settings,
+            gamepads: Vec::new(),
+        };
+        pong.scoreboard = Scoreboard::new(pong.handicap_labels());
+        let mut phase = Box::new(MapsPlayfield::default());
+        phase.enter(&mut pong);
+        pong.phase = Some(phase);
+        pong
+    }
+
+    /// Starts a fresh match by the same rules. The playfield stays mapped
+    /// and the controllers stay as they are.
+    pub fn rematch(&mut self) {
+        let seed = seed_from_env();
+        info!("Rematch with seed {}", seed);
+        self.simulation = Simulation::new(seed, self.settings.clone());
+        self.game = new_match(&self.settings, self.game.rules);
+        let handicaps = self.handicap_labels();
+        self.scoreboard.start_over(handicaps);
+        self.replay = Replay::new(seed, self.settings.clone());
+        self.playback = None;
+        self.changes.clear();
+        self.lag = 0.0;
+    }
+
+    /// Swaps the player's controller for the next kind of controller which
+    /// can be created.
+    pub fn switch_controller(&mut self, player_id: u8) {
+        let mut kind = match self.controllers.get(player_id as usize) {
+            Some((kind, _)) => *kind,
+            None => return,
+        };
+        for _ in 0..Kind::ALL.len() {
+            kind = kind.next();
+            let controller = controllers::new(
+                kind,
+                player_id,
+                self.simulation.paddles[player_id as usize].side,
+                self.camera_positions.as_ref().map(Vec::as_slice),
+            );
+            if let Some(controller) = controller {
+                info!("Player {} now plays with {:?}", player_id, kind);
+                self.controllers[player_id as usize] = (kind, controller);
+                return;
+            }
         }
     }
+
+    /// Gives the player the next handicap preset. The paddles are set up
+    /// with it from the next tick, the head start counts from the next match
+    /// unless the current one hasn't started yet.
+    pub fn switch_handicap(&mut self, player_id: u8) {
+        let handicap = match self.settings.handicaps.get_mut(player_id as usize)
+        {
+            Some(handicap) => handicap,
+            None => return,
+        };
+        *handicap = handicap.next();
+        info!("Player {} now plays with {:?}", player_id, handicap);
+        self.changes.push(Change::Handicap(player_id, *handicap));
+        if !self.game.has_started() {
+            self.game = new_match(&self.settings, self.game.rules);
+        }
+        self.scoreboard.handicaps = self.handicap_labels();
+    }
+
+    /// Returns how the handicaps of each seat of the match are shown, which
+    /// are those of both players of a doubles team.
+    pub fn handicap_labels(&self) -> Vec<String> {
+        (0..self.game.players)
+            .map(|seat| {
+                let labels: Vec<_> = (0..self.settings.players)
+                    .filter(|p| self.settings.team(*p) == seat)
+                    .map(|p| self.settings.handicap(p).label())
+                    .filter(|label| !label.is_empty())
+                    .collect();
+                labels.join(" / ")
+            })
+            .collect()
+    }
+
+    /// Gives a point to given player without them having to score it, e.g.
+    /// when the camera has lost track of a hand. Only players of a
+    /// two-player match play for points.
+    pub fn award_point(&mut self, player_id: u8) {
+        if !self.game.is_duel() {
+            warn!("Points can only be awarded in a two-player match");
+            return;
+        }
+
+        self.game.point(player_id);
+        info!("Player {} awarded a point, {:?}", player_id, self.game);
+        self.scoreboard.point(player_id);
+    }
+
+    /// Takes back the last point. Returns whether there was one.
+    pub fn undo_point(&mut self) -> bool {
+        let undone = self.game.undo();
+        if undone {
+            info!("Point taken back, {:?}", self.game);
+            self.knock_out();
+        }
+        undone
+    }
+
+    /// Starts the score of the match over.
+    pub fn reset_score(&mut self) {
+        info!("Score reset");
+        self.game = new_match(&self.settings, self.game.rules);
+        let handicaps = self.handicap_labels();
+        self.scoreboard.start_over(handicaps);
+        self.knock_out();
+    }
+
+    /// Takes the paddles of the players who are out of the match away and
+    /// brings back those who are in it again after the score has changed,
+    /// from the next tick on.
+    pub fn knock_out(&mut self) {
+        for player_id in 0..self.game.players {
+            let out = self.game.is_out(player_id);
+            let playing = self
+                .changes
+                .iter()
+                .rev()
+                .find_map(|change| match *change {
+                    Change::Playing(p, playing) if p == player_id => {
+                        Some(playing)
+                    }
+                    _ => None,
+                })
+                .unwrap_or_else(|| self.simulation.is_playing(player_id));
+            if playing == out {
+                info!(
+                    "Player {} is {}",
+                    player_id,
+                    if out { "out" } else { "back" }
+                );
+                self.changes.push(Change::Playing(player_id, !out));
+            }
+        }
+    }
+
+    /// Asks the camera to take a new snapshot of the playfield. The table
+    /// should be clear when it does.
+    pub fn remap_playfield(&mut self) {
+        match &self.camera_remap {
+            Some(remap) => remap.store(true, Ordering::SeqCst),
+            None => warn!("There's no camera to map the playfield with"),
+        }
+    }
+
+    /// Stops the robot and saves the replay before the game quits.
+    pub fn quit(&mut self) {
+        self.stop_robot();
+        self.save_replay();
+    }
+
+    /// Saves the inputs played so far into the file given by `PONG_RECORD`
+    /// env var, if it's set.
+    pub fn save_replay(&self) {
+        if let Ok(path) = env::var("PONG_RECORD") {
+            match self.replay.save(&path) {
+                Ok(_) => info!("Replay saved into {}", path),
+                Err(e) => error!("Cannot save replay into {}: {}", path, e),
+            }
+        }
+    }
+
+    /// Stops the robot, if there's one, until it's steered again.
+    pub fn stop_robot(&mut self) {
+        if let Some(robot) = self.robot.as_mut() {
+            if let Err(e) = robot.stop() {
+                error!("Cannot stop the robot: {}", e);
+            }
+        }
+    }
+
+    // Passes the operator's command to the current phase.
+    fn command(&mut self, command: Command) {
+        let mut phase = self.take_phase();
+        let transition = phase.command(self, command);
+        self.follow(phase, transition);
+    }
+
+    fn take_phase(&mut self) -> Box<dyn Phase> {
+        self.phase.take().expect("The game is not in any phase")
+    }
+
+    // Puts the phase which has just run back, or switches to the next one
+    // if it asks to.
+    fn follow(&mut self, mut phase: Box<dyn Phase>, transition: Transition) {
+        match transition {
+            Transition::Stay => self.phase = Some(phase),
+            Transition::To(mut next) => {
+                phase.exit(self);
+                next.enter(self);
+                self.phase = Some(next);
+            }
+        }
+    }
+
+    // Forwards user input to all controllers.
+    fn handle(&mut self, event: Event) {
+        for (_, controller) in self.controllers.iter_mut() {
+            controller.handle(&event);
+        }
+    }
+
+    // Returns the number by which the controllers know the gamepad.
+    fn gamepad(&mut self, id: GamepadId) -> usize {
+        match self.gamepads.iter().position(|gamepad| *gamepad == id) {
+            Some(gamepad) => gamepad,
+            None => {
+                self.gamepads.push(id);
+                self.gamepads.len() - 1
+            }
+        }
+    }
+}
+
+// Sets up the score of a new match by given rules, in which each player has
+// the head start of their handicap. A doubles team has the head start of its
+// back player.
+fn new_match(settings: &Settings, rules: Rules) -> Match {
+    let game = if settings.mode.is_solo() {
+        Match::single(rules, SOLO_PLAYER, settings.players)
+    } else {
+        Match::new(rules, settings.teams())
+    };
+    let head_start = (0..game.players)
+        .map(|seat| settings.handicap(seat).head_start)
+        .collect();
+    game.with_head_start(head_start)
+}
+
+// The seed can be fixed to play the same match again, otherwise it's random.
+fn seed_from_env() -> u64 {
+    env::var("PONG_SEED")
+        .map(|seed| seed.parse().expect("Invalid PONG_SEED"))
+        .unwrap_or_else(|_| rand::random())
 }
 
 impl EventHandler for Pong {
     /// Update the game state or transitions into a new phase.
-    fn update(&mut self, _ctx: &mut Context) -> GameResult<()> {
-        Phase::update(self)
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let mut phase = self.take_phase();
+        match phase.update(self, ctx) {
+            Ok(transition) => {
+                self.follow(phase, transition);
+                Ok(())
+            }
+            Err(e) => {
+                self.phase = Some(phase);
+                Err(e)
+            }
+        }
     }
 
     /// Redraws the GUI.
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
         graphics::clear(ctx, graphics::WHITE);
-        Phase::draw(self, ctx)?;
+        let mut phase = self.take_phase();
+        let drawn = phase.draw(self, ctx);
+        self.phase = Some(phase);
+        drawn?;
         graphics::present(ctx)
     }
 
@@ -68,7 +441,122 @@ impl EventHandler for Pong {
         _xrel: f32,
         _yrel: f32,
     ) {
-        (*self.paddles[0].x.lock().unwrap()) = x as u32;
-        // (*self.paddles[1].x.lock().unwrap()) = x as u32;
+        self.handle(Event::MouseMotion { x });
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
+        self.handle(Event::MouseButtonDown(button));
+    }
+
+    /// Escape quits the game and F1, F2, F8 or F9 switch the controller of
+    /// the first, the second, the third or the fourth player, or with Shift
+    /// their handicap before a match. The operator pauses with P, gives a
+    /// point to the first or the second player with F3 or F4, takes it back
+    /// with F5, resets the score with F6 and maps the playfield again with
+    /// F7. Other keys go to the controllers.
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        keymods: KeyMods,
+        repeat: bool,
+    ) {
+        let shift = keymods.contains(KeyMods::SHIFT);
+        match keycode {
+            KeyCode::Escape => event::quit(ctx),
+            _ if repeat => (),
+            KeyCode::F1 if shift => self.command(Command::Handicap(0)),
+            KeyCode::F2 if shift => self.command(Command::Handicap(1)),
+            KeyCode::F8 if shift => self.command(Command::Handicap(2)),
+            KeyCode::F9 if shift => self.command(Command::Handicap(3)),
+            KeyCode::F1 => self.switch_controller(0),
+            KeyCode::F2 => self.switch_controller(1),
+            KeyCode::F8 => self.switch_controller(2),
+            KeyCode::F9 => self.switch_controller(3),
+            KeyCode::P | KeyCode::Pause => self.command(Command::TogglePause),
+            KeyCode::F3 => self.command(Command::Award(0)),
+            KeyCode::F4 => self.command(Command::Award(1)),
+            KeyCode::F5 => self.command(Command::Undo),
+            KeyCode::F6 => self.command(Command::ResetScore),
+            KeyCode::F7 => self.remap_playfield(),
+            _ => self.handle(Event::KeyDown(keycode)),
+        }
+    }
+
+    fn key_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyCode,
+        _keymods: KeyMods,
+    ) {
+        self.handle(Event::KeyUp(keycode));
+    }
+
+    fn gamepad_axis_event(
+        &mut self,
+        _ctx: &mut Context,
+        axis: Axis,
+        value: f32,
+        id: GamepadId,
+    ) {
+        let gamepad = self.gamepad(id);
+        self.handle(Event::GamepadAxis {
+            gamepad,
+            axis,
+            value,
+        });
+    }
+
+    fn gamepad_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: Button,
+        id: GamepadId,
+    ) {
+        let gamepad = self.gamepad(id);
+        self.handle(Event::GamepadButtonDown { gamepad, button });
+    }
+
+    fn gamepad_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: Button,
+        id: GamepadId,
+    ) {
+        let gamepad = self.gamepad(id);
+        self.handle(Event::GamepadButtonUp { gamepad, button });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handicap::Handicap;
+    use crate::solo::Mode;
+
+    #[test]
+    fn test_new_match() {
+        let settings = Settings {
+            mode: Mode::Squash,
+            handicaps: vec![
+                Handicap::default(),
+                Handicap::parse("points=2").unwrap(),
+            ],
+            ..Default::default()
+        };
+        let rules = Rules::default();
+        let game = new_match(&settings, rules);
+        assert_eq!(Some(SOLO_PLAYER), game.alone);
+        assert_eq!(rules.lives + 2, game.lives_left(SOLO_PLAYER));
+
+        let game = new_match(&Settings::default(), rules);
+        assert_eq!(None, game.alone);
+        assert_eq!(2, game.players);
     }
 }
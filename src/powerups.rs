@@ -0,0 +1,201 @@
+use super::TICKS_PER_SECOND;
+use ggez::graphics::{
+    self, Color, DrawMode, DrawParam, Mesh, Scale, Text, TextFragment, BLACK,
+};
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+use std::env;
+
+/// How large is a power-up on the playfield.
+pub const ITEM_RADIUS: f32 = 24.0;
+
+/// How much wider does a paddle grow.
+pub const GROW_FACTOR: f32 = 1.5;
+
+/// How much narrower does a paddle shrink.
+pub const SHRINK_FACTOR: f32 = 0.6;
+
+/// How much faster do the balls go when sped up.
+pub const SPEED_UP_FACTOR: f32 = 1.3;
+
+/// How much slower do the balls go when slowed down.
+pub const SLOW_DOWN_FACTOR: f32 = 0.7;
+
+/// How large are the labels of the power-ups.
+const LABEL_SIZE: f32 = 16.0;
+
+/// The color of a shield guarding a player's edge.
+pub const SHIELD_COLOR: Color = Color::new(0.3, 0.3, 0.3, 1.0);
+
+/// What happens when a ball passes through a power-up. The player who last
+/// hit the ball gets it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Effect {
+    /// The player's paddle grows wider.
+    Grow,
+
+    /// The other player's paddle shrinks.
+    Shrink,
+
+    /// All balls go faster.
+    SpeedUp,
+
+    /// All balls go slower.
+    SlowDown,
+
+    /// Another ball is served from the middle. It has no duration.
+    ExtraBall,
+
+    /// The other player's controls move their paddle the other way.
+    Reverse,
+
+    /// The next ball which would get past the player's paddle bounces back
+    /// instead.
+    Shield,
+}
+
+impl Effect {
+    pub const ALL: [Effect; 7] = [
+        Effect::Grow,
+        Effect::Shrink,
+        Effect::SpeedUp,
+        Effect::SlowDown,
+        Effect::ExtraBall,
+        Effect::Reverse,
+        Effect::Shield,
+    ];
+
+    /// Whether the effect applies to the other player than the one who got
+    /// the power-up.
+    pub fn hurts_other(self) -> bool {
+        self == Effect::Shrink || self == Effect::Reverse
+    }
+
+    /// Short name shown on the playfield.
+    pub fn label(self) -> &'static str {
+        match self {
+            Effect::Grow => "WIDE",
+            Effect::Shrink => "NARROW",
+            Effect::SpeedUp => "FAST",
+            Effect::SlowDown => "SLOW",
+            Effect::ExtraBall => "+1",
+            Effect::Reverse => "SWAP",
+            Effect::Shield => "SHIELD",
+        }
+    }
+}
+
+/// How often do power-ups appear and how long do they last.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PowerUps {
+    /// A power-up appears every this many ticks of play, if at all.
+    pub every: Option<u32>,
+
+    /// For how many ticks does an effect last.
+    pub duration: u32,
+
+    /// For how many ticks does a power-up wait on the playfield before it
+    /// disappears.
+    pub lifetime: u32,
+
+    /// At most this many power-ups wait on the playfield at once.
+    pub max_items: usize,
+}
+
+impl Default for PowerUps {
+    /// No power-ups.
+    fn default() -> Self {
+        Self {
+            every: None,
+            duration: 10 * TICKS_PER_SECOND,
+            lifetime: 15 * TICKS_PER_SECOND,
+            max_items: 2,
+        }
+    }
+}
+
+impl PowerUps {
+    /// Reads how many seconds apart do power-ups appear from
+    /// `PONG_POWERUPS_SECONDS` env var, by default they don't. The effects
+    /// last `PONG_POWERUP_DURATION` seconds (default 10).
+    pub fn from_env() -> Self {
+        let read = |var: &str| -> Option<u32> {
+            env::var(var).ok().map(|value| {
+                let seconds: f32 =
+                    value.parse().unwrap_or_else(|_| panic!("Invalid {}", var));
+                (seconds * TICKS_PER_SECOND as f32) as u32
+            })
+        };
+
+        let default = Self::default();
+        Self {
+            every: read("PONG_POWERUPS_SECONDS"),
+            duration: read("PONG_POWERUP_DURATION").unwrap_or(default.duration),
+            ..default
+        }
+    }
+}
+
+/// A power-up waiting on the playfield for a ball to pass through it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item {
+    pub effect: Effect,
+
+    pub center: (f32, f32),
+
+    /// How many ticks until the power-up disappears.
+    pub ticks_left: u32,
+}
+
+impl Item {
+    /// Whether a ball with given center and radius touches the power-up.
+    pub fn touches(&self, (x, y): (f32, f32), radius: f32) -> bool {
+        let (dx, dy) = (x - self.center.0, y - self.center.1);
+        (dx * dx + dy * dy).sqrt() < ITEM_RADIUS + radius
+    }
+
+    /// Draws the power-up as a ring with its label inside.
+    pub fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+        let center = Point2::new(self.center.0, self.center.1);
+        let ring = Mesh::new_circle(
+            ctx,
+            DrawMode::stroke(3.0),
+            center,
+            ITEM_RADIUS,
+            0.5,
+            BLACK,
+        )?;
+        graphics::draw(ctx, &ring, DrawParam::default())?;
+
+        let label = Text::new(
+            TextFragment::new(self.effect.label())
+                .color(BLACK)
+                .scale(Scale::uniform(LABEL_SIZE)),
+        );
+        let (width, height) = label.dimensions(ctx);
+        let dest = Point2::new(
+            center.x - width as f32 / 2.0,
+            center.y - height as f32 / 2.0,
+        );
+        graphics::draw(ctx, &label, DrawParam::default().dest(dest))
+    }
+}
+
+/// An effect of a power-up which is in force.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Active {
+    pub effect: Effect,
+
+    /// Which player does the effect apply to.
+    pub player_id: u8,
+
+    /// How many ticks until the effect wears off.
+    pub ticks_left: u32,
+}
+
+impl Active {
+    /// Returns how many whole seconds are left, as shown to the players.
+    pub fn seconds_left(self) -> u32 {
+        (self.ticks_left + TICKS_PER_SECOND - 1) / TICKS_PER_SECOND
+    }
+}
@@ -0,0 +1,121 @@
+use super::solo::{Mode, SQUASH_SPEED_UP};
+use std::env;
+
+/// How the balls speed up as the paddles keep returning them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Curve {
+    /// The balls keep their pace however long the rally is.
+    Flat,
+
+    /// Each hit adds this much to the pace of the balls.
+    Linear(f32),
+
+    /// Each hit multiplies the pace of the balls by this much.
+    Exponential(f32),
+}
+
+/// How fast do the balls go in a rally. Their pace depends on how many times
+/// in a row the paddles have hit them, on top of the speed they gain in the
+/// bounces and lose again afterwards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rally {
+    pub curve: Curve,
+
+    /// The pace the curve stops at, as a multiple of the usual one.
+    pub max_pace: f32,
+}
+
+impl Default for Rally {
+    /// Each hit adds 3% of the usual pace, up to 1.8 times the usual pace.
+    fn default() -> Self {
+        Self {
+            curve: Curve::Linear(0.03),
+            max_pace: 1.8,
+        }
+    }
+}
+
+impl Rally {
+    /// In squash, each return multiplies the pace by `SQUASH_SPEED_UP`.
+    pub fn squash() -> Self {
+        Self {
+            curve: Curve::Exponential(SQUASH_SPEED_UP),
+            max_pace: 2.5,
+        }
+    }
+
+    /// Reads the rally curve from `PONG_RALLY_CURVE` env var, which is
+    /// either "flat", "linear" or "exponential", and `PONG_RALLY_STEP`, which
+    /// is how many percent of the pace each hit adds (default 3 for linear
+    /// and 5 for exponential). The pace stops at `PONG_RALLY_MAX_PACE`. What
+    /// isn't set is as in the default of the mode.
+    pub fn from_env(mode: Mode) -> Self {
+        let default = if mode == Mode::Squash {
+            Self::squash()
+        } else {
+            Self::default()
+        };
+        let step: Option<f32> = env::var("PONG_RALLY_STEP").ok().map(|value| {
+            value.parse::<f32>().expect("Invalid PONG_RALLY_STEP") / 100.0
+        });
+        let curve = match env::var("PONG_RALLY_CURVE")
+            .as_ref()
+            .map(String::as_str)
+        {
+            Err(_) => match (default.curve, step) {
+                (Curve::Linear(_), Some(step)) => Curve::Linear(step),
+                (Curve::Exponential(_), Some(step)) => {
+                    Curve::Exponential(1.0 + step)
+                }
+                (curve, _) => curve,
+            },
+            Ok("flat") => Curve::Flat,
+            Ok("linear") => Curve::Linear(step.unwrap_or(0.03)),
+            Ok("exponential") => Curve::Exponential(1.0 + step.unwrap_or(0.05)),
+            Ok(other) => panic!("Unknown rally curve {}", other),
+        };
+
+        Self {
+            curve,
+            max_pace: env::var("PONG_RALLY_MAX_PACE")
+                .map(|value| {
+                    value.parse().expect("Invalid PONG_RALLY_MAX_PACE")
+                })
+                .unwrap_or(default.max_pace),
+        }
+    }
+
+    /// Returns how many times as fast as usual do the balls go after given
+    /// number of hits in a row.
+    pub fn pace(self, hits: u32) -> f32 {
+        let pace = match self.curve {
+            Curve::Flat => 1.0,
+            Curve::Linear(step) => 1.0 + step * hits as f32,
+            Curve::Exponential(factor) => factor.powi(hits as i32),
+        };
+        pace.min(self.max_pace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pace() {
+        let linear = Rally::default();
+        assert!((linear.pace(0) - 1.0).abs() < 0.001);
+        assert!((linear.pace(10) - 1.3).abs() < 0.001);
+        assert!((linear.pace(1000) - linear.max_pace).abs() < 0.001);
+
+        let squash = Rally::squash();
+        assert!((squash.pace(2) - 1.1025).abs() < 0.001);
+        assert!((squash.pace(100) - squash.max_pace).abs() < 0.001);
+
+        let flat = Rally {
+            curve: Curve::Flat,
+            max_pace: 2.0,
+        };
+        assert!((flat.pace(50) - 1.0).abs() < 0.001);
+    }
+}
@@ -0,0 +1,274 @@
+use super::ball::Ball;
+use super::camera::Tracking;
+use super::markers::Marker;
+use super::paddle::Paddle;
+use super::WINDOW_SIZE;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// How many percent of the robot's full speed per pixel of distance between
+/// the robot and its target.
+const GAIN: f32 = 0.5;
+
+/// If the robot is closer to its target than this many pixels, it stops.
+const DEAD_ZONE: f32 = 5.0;
+
+/// A small robot acting as a paddle. It carries a marker which the camera
+/// tracks, therefore we know where it really is and which way it's facing.
+/// The marker also moves the paddle in the game.
+///
+/// The robot listens on a serial port for ASCII lines terminated by `\n`:
+///
+/// ```
+/// M <speed>
+/// ```
+///
+/// where speed is an integer in interval <-100; 100>, the percentage of full
+/// speed of both motors. Positive speed drives the robot forwards, that is in
+/// the direction of the top edge of its marker.
+pub struct Robot<P: Write = File> {
+    /// Which player does the robot play for.
+    pub player_id: u8,
+
+    // Serial port the robot listens on.
+    port: P,
+
+    // Which marker is on top of the robot.
+    marker_id: u8,
+
+    // Latest markers seen by the camera.
+    markers: Arc<Mutex<Vec<Marker>>>,
+
+    // The last speed we sent so that we only send changes.
+    speed: Option<i8>,
+}
+
+impl Robot {
+    /// Connects to the robot if `PONG_ROBOT` env var is set to the path of a
+    /// serial port. The robot plays for player given by `PONG_ROBOT_PLAYER`
+    /// (default 1) and the port runs at `PONG_ROBOT_BAUD` (default 9600).
+    pub fn from_env(
+        tracking: &Tracking,
+        markers: Arc<Mutex<Vec<Marker>>>,
+    ) -> Option<Self> {
+        let path = env::var("PONG_ROBOT").ok()?;
+        let player_id: u8 = env::var("PONG_ROBOT_PLAYER")
+            .map(|id| id.parse().expect("Invalid robot player"))
+            .unwrap_or(1);
+        let baud =
+            match env::var("PONG_ROBOT_BAUD").as_ref().map(String::as_str) {
+                Err(_) | Ok("9600") => libc::B9600,
+                Ok("19200") => libc::B19200,
+                Ok("38400") => libc::B38400,
+                Ok("57600") => libc::B57600,
+                Ok("115200") => libc::B115200,
+                Ok(other) => panic!("Unsupported baud rate {}", other),
+            };
+        let marker_id = match tracking {
+            Tracking::Markers(ids) => *ids
+                .get(player_id as usize)
+                .expect("No marker is bound to the robot's player"),
+            Tracking::Columns => {
+                panic!("The robot must be tracked by a marker")
+            }
+        };
+
+        info!("Connecting to robot for player {} on {}", player_id, path);
+        let robot = Self::open(&path, baud, player_id, marker_id, markers)
+            .expect("Cannot open robot's serial port");
+        Some(robot)
+    }
+
+    /// Opens the serial port in raw mode with given baud rate, such as
+    /// `libc::B9600`.
+    pub fn open(
+        path: impl AsRef<Path>,
+        baud: libc::speed_t,
+        player_id: u8,
+        marker_id: u8,
+        markers: Arc<Mutex<Vec<Marker>>>,
+    ) -> io::Result<Self> {
+        let port = OpenOptions::new().read(true).write(true).open(path)?;
+
+        // Turns off all processing of the characters, e.g. converting new
+        // lines, so that the robot gets exactly what we send.
+        unsafe {
+            let mut termios = std::mem::zeroed();
+            if libc::tcgetattr(port.as_raw_fd(), &mut termios) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            libc::cfmakeraw(&mut termios);
+            if libc::cfsetspeed(&mut termios, baud) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::tcsetattr(port.as_raw_fd(), libc::TCSANOW, &termios) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(Self::new(port, player_id, marker_id, markers))
+    }
+}
+
+impl<P: Write> Robot<P> {
+    /// Creates a robot which listens on given port.
+    pub fn new(
+        port: P,
+        player_id: u8,
+        marker_id: u8,
+        markers: Arc<Mutex<Vec<Marker>>>,
+    ) -> Self {
+        Self {
+            port,
+            player_id,
+            marker_id,
+            markers,
+            speed: None,
+        }
+    }
+
+    /// Sends the robot towards the point where the ball is going to cross its
+    /// paddle's line. If the ball is moving away, the robot returns to the
+    /// middle. If the camera lost the robot, it stops.
+    pub fn steer(&mut self, ball: &Ball, paddle: &Paddle) -> io::Result<()> {
+        let target = ball
+            .predict_x(paddle.ball_line(ball.radius))
+            .unwrap_or(WINDOW_SIZE.0 / 2.0);
+
+        let marker = self
+            .markers
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|marker| marker.id == self.marker_id)
+            .copied();
+        let speed = match marker {
+            Some(marker) => speed_towards(&marker, target),
+            None => 0,
+        };
+
+        self.drive(speed)
+    }
+
+    /// Stops the robot, e.g. when the game is paused or quits.
+    pub fn stop(&mut self) -> io::Result<()> {
+        self.drive(0)
+    }
+
+    // Sends the speed to the robot unless it's already going at it.
+    fn drive(&mut self, speed: i8) -> io::Result<()> {
+        if self.speed != Some(speed) {
+            writeln!(self.port, "M {}", speed)?;
+            self.port.flush()?;
+            self.speed = Some(speed);
+        }
+
+        Ok(())
+    }
+}
+
+// Calculates what speed should the robot go to reach the target x coordinate.
+// The robot is driving along the x axis, but it can be placed either way. If
+// its marker faces left, it has to drive backwards to go right.
+fn speed_towards(marker: &Marker, target: f32) -> i8 {
+    let distance = target - marker.position.0;
+    if distance.abs() < DEAD_ZONE {
+        return 0;
+    }
+
+    let facing = marker.heading.cos().signum();
+    (distance * GAIN * facing).max(-100.0).min(100.0) as i8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::io::FromRawFd;
+
+    fn marker(x: f32, heading: f32) -> Arc<Mutex<Vec<Marker>>> {
+        Arc::new(Mutex::new(vec![Marker {
+            id: 5,
+            position: (x, WINDOW_SIZE.1 - 20.0),
+            heading,
+        }]))
+    }
+
+    #[test]
+    fn test_speed_towards() {
+        let right = Marker {
+            id: 0,
+            position: (100.0, 0.0),
+            heading: 0.0,
+        };
+        let left = Marker {
+            heading: std::f32::consts::PI,
+            ..right
+        };
+        assert_eq!(0, speed_towards(&right, 102.0));
+        assert_eq!(50, speed_towards(&right, 200.0));
+        assert_eq!(-50, speed_towards(&left, 200.0));
+        assert_eq!(-100, speed_towards(&right, -500.0));
+    }
+
+    #[test]
+    fn test_sends_only_changes() {
+        let ball = Ball::default();
+        let paddle = Paddle::new(1);
+        let mut robot = Robot::new(Vec::new(), 1, 5, marker(0.0, 0.0));
+        robot.steer(&ball, &paddle).unwrap();
+        robot.steer(&ball, &paddle).unwrap();
+        assert_eq!(b"M 100\n".to_vec(), robot.port);
+
+        robot.markers.lock().unwrap().clear();
+        robot.steer(&ball, &paddle).unwrap();
+        assert_eq!(b"M 100\nM 0\n".to_vec(), robot.port);
+    }
+
+    #[test]
+    fn test_stop() {
+        let ball = Ball::default();
+        let paddle = Paddle::new(1);
+        let mut robot = Robot::new(Vec::new(), 1, 5, marker(0.0, 0.0));
+        robot.steer(&ball, &paddle).unwrap();
+        robot.stop().unwrap();
+        robot.stop().unwrap();
+        assert_eq!(b"M 100\nM 0\n".to_vec(), robot.port);
+    }
+
+    #[test]
+    fn test_pseudo_terminal() {
+        // Opens a pseudo terminal and lets the robot talk to its slave side
+        // as if it was a serial port.
+        let master = unsafe {
+            let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            assert!(fd >= 0);
+            assert_eq!(0, libc::grantpt(fd));
+            assert_eq!(0, libc::unlockpt(fd));
+            File::from_raw_fd(fd)
+        };
+        let slave = unsafe {
+            CStr::from_ptr(libc::ptsname(master.as_raw_fd()))
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        // A baud rate the port doesn't support is refused.
+        assert!(Robot::open(&slave, 12345, 1, 5, marker(0.0, 0.0)).is_err());
+
+        let ball = Ball::default();
+        let paddle = Paddle::new(1);
+        let mut robot =
+            Robot::open(slave, libc::B9600, 1, 5, marker(1000.0, 0.0)).unwrap();
+        robot.steer(&ball, &paddle).unwrap();
+
+        let mut line = String::new();
+        BufReader::new(master).read_line(&mut line).unwrap();
+        assert_eq!("M -100\n", line);
+    }
+}
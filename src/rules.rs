@@ -0,0 +1,558 @@
+use super::TICKS_PER_SECOND;
+use std::env;
+use std::str::FromStr;
+
+/// How a match is won.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rules {
+    /// How many points win a set.
+    pub points_to_win: u32,
+
+    /// Whether the set goes on until a player leads by two points.
+    pub win_by_two: bool,
+
+    /// Whether the next point wins the set once both players are one point
+    /// away from winning it. Overrides winning by two.
+    pub golden_point: bool,
+
+    /// The match is best of this many sets.
+    pub sets: u32,
+
+    /// How many ticks does the match last at most. When the time is up, the
+    /// player who leads wins. If it's a tie, the next point wins.
+    pub time_limit: Option<u32>,
+
+    /// How many goals knock a player out of a match of more than two
+    /// players. The last player left wins.
+    pub lives: u32,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            points_to_win: 11,
+            win_by_two: true,
+            golden_point: false,
+            sets: 1,
+            time_limit: None,
+            lives: 5,
+        }
+    }
+}
+
+impl Rules {
+    /// Reads the rules from `PONG_POINTS`, `PONG_WIN_BY_TWO`,
+    /// `PONG_GOLDEN_POINT`, `PONG_SETS`, `PONG_TIME_LIMIT` and `PONG_LIVES`
+    /// env vars. The time limit is in seconds.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let time_limit: Option<f32> = read("PONG_TIME_LIMIT");
+        Self {
+            points_to_win: read("PONG_POINTS").unwrap_or(default.points_to_win),
+            win_by_two: read("PONG_WIN_BY_TWO").unwrap_or(default.win_by_two),
+            golden_point: read("PONG_GOLDEN_POINT")
+                .unwrap_or(default.golden_point),
+            sets: read("PONG_SETS").unwrap_or(default.sets),
+            time_limit: time_limit
+                .map(|seconds| (seconds * TICKS_PER_SECOND as f32) as u32),
+            lives: read("PONG_LIVES").unwrap_or(default.lives),
+        }
+    }
+}
+
+fn read<T: FromStr>(var: &str) -> Option<T> {
+    env::var(var).ok().map(|value| {
+        value
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid {}: {}", var, value))
+    })
+}
+
+/// Score of a match played by given rules. Two players play for points and
+/// sets, more players play for their lives. A lone player plays for their
+/// own score until they run out of lives.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Match {
+    pub rules: Rules,
+
+    /// How many players take part.
+    pub players: u8,
+
+    /// Points of each player in the current set of a two-player match.
+    pub points: [u32; 2],
+
+    /// How many sets has each player of a two-player match won.
+    pub sets: [u32; 2],
+
+    /// How many goals has each player let in.
+    pub conceded: Vec<u32>,
+
+    /// The player who plays alone in a single-player game. The other seats
+    /// are empty.
+    pub alone: Option<u8>,
+
+    /// Score of the lone player, e.g. how many bricks they have broken.
+    pub score: u32,
+
+    /// The best score the lone player has had in the game.
+    pub best: u32,
+
+    /// How many points ahead does each player start every set, or how many
+    /// more lives do they have.
+    pub head_start: Vec<u32>,
+
+    /// For how many ticks has the match been played.
+    pub ticks: u32,
+
+    /// Who has won the match, if it's over.
+    pub winner: Option<u8>,
+
+    // The score before each point, so that points can be taken back.
+    history: Vec<Snapshot>,
+}
+
+// The score of a match at one moment.
+#[derive(Clone, Debug, PartialEq)]
+struct Snapshot {
+    points: [u32; 2],
+    sets: [u32; 2],
+    conceded: Vec<u32>,
+    ticks: u32,
+}
+
+impl Match {
+    pub fn new(rules: Rules, players: u8) -> Self {
+        Self {
+            rules,
+            players,
+            points: [0, 0],
+            sets: [0, 0],
+            conceded: vec![0; usize::from(players)],
+            alone: None,
+            score: 0,
+            best: 0,
+            head_start: vec![0; usize::from(players)],
+            ticks: 0,
+            winner: None,
+            history: Vec::new(),
+        }
+    }
+
+    /// Sets up a single-player game of given player at a table of given
+    /// number of seats.
+    pub fn single(rules: Rules, player_id: u8, players: u8) -> Self {
+        Self {
+            alone: Some(player_id),
+            ..Self::new(rules, players)
+        }
+    }
+
+    /// Gives each player given head start, in the order of their ids.
+    pub fn with_head_start(mut self, head_start: Vec<u32>) -> Self {
+        self.head_start = head_start;
+        self.points = self.starting_points();
+        self
+    }
+
+    /// Whether any time has been played or any point scored in the match.
+    pub fn has_started(&self) -> bool {
+        self.ticks > 0 || !self.history.is_empty()
+    }
+
+    /// Whether two players play against each other for points.
+    pub fn is_duel(&self) -> bool {
+        self.players == 2 && self.alone.is_none()
+    }
+
+    /// Whether the match is over. A single-player game is over once the
+    /// player has won it, run out of lives or time.
+    pub fn is_over(&self) -> bool {
+        self.winner.is_some()
+            || self.alone.map_or(false, |player_id| {
+                self.lives_left(player_id) == 0 || self.time_left() == Some(0)
+            })
+    }
+
+    /// Counts a goal given player has let in. In a two-player match the
+    /// other player gets a point, otherwise the player loses a life. Returns
+    /// the winner if the goal has decided the match.
+    pub fn concede(&mut self, player_id: u8) -> Option<u8> {
+        if self.is_duel() {
+            return self.point(1 - player_id);
+        }
+        if self.is_over() {
+            return self.winner;
+        }
+
+        self.history.push(self.snapshot());
+        self.conceded[usize::from(player_id)] += 1;
+        if self.alone.is_some() {
+            return None;
+        }
+
+        let left: Vec<_> =
+            (0..self.players).filter(|p| !self.is_out(*p)).collect();
+        if left.len() == 1 {
+            self.winner = Some(left[0]);
+        } else if self.time_left() == Some(0) {
+            self.winner = self.leader();
+        }
+
+        self.winner
+    }
+
+    /// How many more goals can the player let in before they are out.
+    pub fn lives_left(&self, player_id: u8) -> u32 {
+        let p = usize::from(player_id);
+        (self.rules.lives + self.head_start[p]).saturating_sub(self.conceded[p])
+    }
+
+    /// Whether the player has been knocked out of a match of more than two
+    /// players. Nobody sits in the empty seats of a single-player game.
+    pub fn is_out(&self, player_id: u8) -> bool {
+        match self.alone {
+            Some(alone) => player_id != alone,
+            None => self.players > 2 && self.lives_left(player_id) == 0,
+        }
+    }
+
+    /// Updates the score of the lone player and their best score.
+    pub fn set_score(&mut self, score: u32) {
+        self.score = score;
+        self.best = self.best.max(score);
+    }
+
+    /// The lone player has won the game, e.g. by breaking all the bricks.
+    pub fn win(&mut self) {
+        if !self.is_over() {
+            self.winner = self.alone;
+        }
+    }
+
+    /// Gives a point to given player of a two-player match. Returns the
+    /// winner if the point has decided the match.
+    pub fn point(&mut self, player_id: u8) -> Option<u8> {
+        if self.winner.is_some() {
+            return self.winner;
+        }
+
+        let p = player_id as usize;
+        self.history.push(self.snapshot());
+        self.conceded[1 - p] += 1;
+        self.points[p] += 1;
+
+        if self.time_left() == Some(0) {
+            self.winner = Some(player_id);
+        } else if self.wins_set(p) {
+            // The points of the last set stay for the final score.
+            self.sets[p] += 1;
+            if self.sets[p] > self.rules.sets / 2 {
+                self.winner = Some(player_id);
+            } else {
+                self.points = self.starting_points();
+            }
+        }
+
+        self.winner
+    }
+
+    /// Takes back the last point, even if it has decided the match. The
+    /// clock goes back to when the point was scored, so that a match won
+    /// when the time was up goes on. Returns whether there was a point to
+    /// take back.
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(snapshot) => {
+                self.points = snapshot.points;
+                self.sets = snapshot.sets;
+                self.conceded = snapshot.conceded;
+                self.ticks = snapshot.ticks;
+                self.winner = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Lets the time of the match pass by one tick. Returns the winner if
+    /// the time is up and one of the players leads.
+    pub fn tick(&mut self) -> Option<u8> {
+        if self.is_over() || self.time_left() == Some(0) {
+            return self.winner;
+        }
+
+        self.ticks += 1;
+        if self.time_left() == Some(0) && self.alone.is_none() {
+            self.winner = self.leader();
+        }
+
+        self.winner
+    }
+
+    /// How many ticks are left if the match is time limited.
+    pub fn time_left(&self) -> Option<u32> {
+        self.rules
+            .time_limit
+            .map(|limit| limit.saturating_sub(self.ticks))
+    }
+
+    // Returns the score as it is now.
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            points: self.points,
+            sets: self.sets,
+            conceded: self.conceded.clone(),
+            ticks: self.ticks,
+        }
+    }
+
+    // Who leads by sets or, if the sets are even, by points. With more than
+    // two players, who has the most lives left.
+    fn leader(&self) -> Option<u8> {
+        let score = |p: u8| {
+            if self.players == 2 {
+                let p = usize::from(p);
+                (self.sets[p], self.points[p])
+            } else {
+                (0, self.lives_left(p))
+            }
+        };
+        let best = (0..self.players).map(score).max()?;
+        let leaders: Vec<_> =
+            (0..self.players).filter(|p| score(*p) == best).collect();
+        if leaders.len() == 1 {
+            Some(leaders[0])
+        } else {
+            None
+        }
+    }
+
+    // Returns the points of a two-player match at the start of a set.
+    fn starting_points(&self) -> [u32; 2] {
+        match self.head_start.as_slice() {
+            [first, second] => [*first, *second],
+            _ => [0, 0],
+        }
+    }
+
+    // Whether the player has just won the current set.
+    fn wins_set(&self, p: usize) -> bool {
+        let own = self.points[p];
+        let other = self.points[1 - p];
+        let margin = if self.rules.win_by_two && !self.rules.golden_point {
+            2
+        } else {
+            1
+        };
+
+        own >= self.rules.points_to_win && own >= other + margin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Plays the points in given order.
+    fn play(rules: Rules, points: &[u8]) -> Match {
+        let mut game = Match::new(rules, 2);
+        for player_id in points {
+            game.point(*player_id);
+        }
+        game
+    }
+
+    fn rules(points_to_win: u32) -> Rules {
+        Rules {
+            points_to_win,
+            win_by_two: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_first_to_n_points() {
+        assert_eq!(None, play(rules(3), &[0, 1, 0, 1]).winner);
+        assert_eq!(Some(1), play(rules(3), &[0, 1, 0, 1, 1]).winner);
+    }
+
+    #[test]
+    fn test_win_by_two() {
+        let rules = Rules {
+            win_by_two: true,
+            ..rules(3)
+        };
+        assert_eq!(None, play(rules, &[0, 1, 0, 1, 1]).winner);
+        assert_eq!(None, play(rules, &[0, 1, 0, 1, 1, 0, 0]).winner);
+        assert_eq!(Some(0), play(rules, &[0, 1, 0, 1, 1, 0, 0, 0]).winner);
+    }
+
+    #[test]
+    fn test_golden_point() {
+        let rules = Rules {
+            win_by_two: true,
+            golden_point: true,
+            ..rules(3)
+        };
+        assert_eq!(Some(1), play(rules, &[0, 1, 0, 1, 1]).winner);
+    }
+
+    #[test]
+    fn test_best_of_sets() {
+        let rules = Rules {
+            sets: 3,
+            ..rules(2)
+        };
+        let game = play(rules, &[0, 0, 1, 1]);
+        assert_eq!([1, 1], game.sets);
+        assert_eq!([0, 0], game.points);
+        assert_eq!(None, game.winner);
+
+        let game = play(rules, &[0, 0, 1, 1, 1]);
+        assert_eq!([0, 1], game.points);
+
+        let game = play(rules, &[0, 0, 1, 1, 1, 1]);
+        assert_eq!(Some(1), game.winner);
+        assert_eq!([0, 2], game.points);
+        assert_eq!([1, 2], game.sets);
+    }
+
+    #[test]
+    fn test_undo() {
+        let rules = Rules {
+            sets: 3,
+            ..rules(2)
+        };
+        let mut game = play(rules, &[0, 0, 1, 1, 1, 1]);
+        assert!(game.undo());
+        assert_eq!(None, game.winner);
+        assert_eq!([0, 1], game.points);
+        assert_eq!([1, 1], game.sets);
+
+        assert!(game.undo());
+        assert!(game.undo());
+        assert_eq!([0, 1], game.points);
+        assert_eq!([1, 0], game.sets);
+
+        let mut game = Match::new(rules, 2);
+        assert!(!game.undo());
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let rules = Rules {
+            time_limit: Some(10),
+            ..rules(11)
+        };
+        let mut game = play(rules, &[0, 1]);
+        for _ in 0..9 {
+            assert_eq!(None, game.tick());
+        }
+        assert_eq!(Some(1), game.time_left());
+
+        // It's a tie when the time is up, the next point wins.
+        assert_eq!(None, game.tick());
+        assert_eq!(Some(0), game.time_left());
+        assert_eq!(None, game.tick());
+        assert_eq!(Some(1), game.point(1));
+
+        let mut game = play(rules, &[0, 1, 0]);
+        for _ in 0..9 {
+            game.tick();
+        }
+        assert_eq!(Some(0), game.tick());
+
+        // Taking back the lead winds the clock back to the point.
+        let mut game = Match::new(rules, 2);
+        for _ in 0..4 {
+            game.tick();
+        }
+        game.point(0);
+        for _ in 0..6 {
+            game.tick();
+        }
+        assert_eq!(Some(0), game.winner);
+        assert!(game.undo());
+        assert_eq!(Some(6), game.time_left());
+        assert_eq!(None, game.tick());
+        assert_eq!(None, game.point(1));
+    }
+
+    #[test]
+    fn test_last_player_left_wins() {
+        let rules = Rules {
+            lives: 2,
+            ..Default::default()
+        };
+        let mut game = Match::new(rules, 4);
+        for player_id in &[2, 0, 2] {
+            assert_eq!(None, game.concede(*player_id));
+        }
+        assert!(game.is_out(2));
+        assert_eq!(1, game.lives_left(0));
+
+        assert_eq!(None, game.concede(0));
+        assert!(game.undo());
+        assert!(!game.is_out(0));
+        assert_eq!(None, game.concede(0));
+        assert_eq!(None, game.concede(1));
+        assert_eq!(Some(3), game.concede(1));
+        assert_eq!(Some(3), game.concede(1));
+        assert_eq!(vec![2, 2, 2, 0], game.conceded);
+
+        // Two players play for points instead.
+        let mut game = Match::new(rules, 2);
+        game.concede(0);
+        assert_eq!([0, 1], game.points);
+        assert!(!game.is_out(0));
+    }
+
+    #[test]
+    fn test_single_player() {
+        let rules = Rules {
+            lives: 2,
+            ..Default::default()
+        };
+        let mut game = Match::single(rules, 1, 2);
+        assert!(game.is_out(0));
+        assert!(!game.is_out(1));
+
+        game.set_score(7);
+        game.set_score(0);
+        assert_eq!((0, 7), (game.score, game.best));
+        assert_eq!(None, game.concede(1));
+        assert!(!game.is_over());
+        assert_eq!(None, game.concede(1));
+        assert!(game.is_over());
+        assert_eq!(None, game.winner);
+        assert_eq!([0, 0], game.points);
+
+        assert!(game.undo());
+        game.win();
+        assert_eq!(Some(1), game.winner);
+    }
+
+    #[test]
+    fn test_head_start() {
+        let rules = Rules {
+            sets: 3,
+            lives: 2,
+            ..rules(3)
+        };
+        let mut game = Match::new(rules, 2).with_head_start(vec![0, 2]);
+        assert_eq!([0, 2], game.points);
+        game.concede(0);
+        assert_eq!([0, 1], game.sets);
+
+        // Every set starts with the head start.
+        assert_eq!([0, 2], game.points);
+        game.concede(1);
+        assert_eq!([1, 2], game.points);
+
+        let mut game = Match::new(rules, 3).with_head_start(vec![1, 0, 0]);
+        game.concede(0);
+        game.concede(0);
+        assert_eq!(1, game.lives_left(0));
+        assert!(!game.is_out(0));
+    }
+}
@@ -0,0 +1,299 @@
+use super::paddle::Side;
+use super::rules::Match;
+use super::{TICKS_PER_SECOND, WINDOW_SIZE};
+use ggez::graphics::{self, Color, DrawParam, Scale, Text, TextFragment};
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+use std::f32::consts::PI;
+
+/// How large are the digits of the score.
+const SCORE_SIZE: f32 = 64.0;
+
+/// How large are the handicaps under the score.
+const HANDICAP_SIZE: f32 = 24.0;
+
+/// How large is the length of the rally under the time left.
+const RALLY_SIZE: f32 = 24.0;
+
+/// How large is the text announcing a point.
+const POINT_SIZE: f32 = 96.0;
+
+/// How many ticks is a point announced for.
+const POINT_TICKS: u32 = 3 * TICKS_PER_SECOND / 2;
+
+/// How far from the edges of the window is the score drawn.
+const MARGIN: f32 = 20.0;
+
+/// Shows the score to all players. The players face each other across the
+/// table, hence each of them has their own score which reads upright from
+/// their side. The first player sits by the top edge of the window.
+#[derive(Default)]
+pub struct Scoreboard {
+    // Who has scored the last point and for how many more ticks is it
+    // announced.
+    point: Option<(u8, u32)>,
+
+    /// How is the handicap of each player shown, empty if they have none.
+    pub handicaps: Vec<String>,
+
+    // How many times in a row have the paddles hit the ball in the current
+    // rally and in the longest one since the game started.
+    rally: u32,
+    longest_rally: u32,
+}
+
+impl Scoreboard {
+    /// Creates a scoreboard which shows given handicaps under the score of
+    /// each player.
+    pub fn new(handicaps: Vec<String>) -> Self {
+        Self {
+            handicaps,
+            ..Default::default()
+        }
+    }
+
+    /// Clears the scoreboard for a new match with given handicaps. The
+    /// longest rally stays on record.
+    pub fn start_over(&mut self, handicaps: Vec<String>) {
+        *self = Self {
+            longest_rally: self.longest_rally,
+            ..Self::new(handicaps)
+        };
+    }
+
+    /// Shows how many times in a row have the paddles hit the ball in the
+    /// current rally and keeps the record of the longest one.
+    pub fn rally(&mut self, hits: u32) {
+        self.rally = hits;
+        self.longest_rally = self.longest_rally.max(hits);
+    }
+
+    /// Announces that given player has scored.
+    pub fn point(&mut self, player_id: u8) {
+        self.point = Some((player_id, POINT_TICKS));
+    }
+
+    /// Lets the announcement of the last point fade away.
+    pub fn tick(&mut self) {
+        self.point = match self.point {
+            Some((player_id, left)) if left > 1 => Some((player_id, left - 1)),
+            _ => None,
+        };
+    }
+
+    /// Draws the score of each player, their own points first. The sets
+    /// follow in brackets if more than one set is played, the player's
+    /// handicap is under them and the time left is on the right if the
+    /// match is time limited. The length of the rally is under the time
+    /// left once the paddles have hit a ball.
+    pub fn draw(&self, ctx: &mut Context, game: &Match) -> GameResult<()> {
+        for player_id in 0..game.players {
+            if game.alone.map_or(false, |alone| alone != player_id) {
+                continue;
+            }
+            let score = text(score(game, player_id), SCORE_SIZE);
+            let position = (MARGIN, WINDOW_SIZE.1 / 2.0 + MARGIN);
+            draw_facing(ctx, &score, player_id, position, graphics::BLACK)?;
+
+            match self.handicaps.get(usize::from(player_id)) {
+                Some(handicap) if !handicap.is_empty() => {
+                    let handicap = text(handicap.clone(), HANDICAP_SIZE);
+                    let (_, height) = score.dimensions(ctx);
+                    let position = (MARGIN, position.1 + height as f32);
+                    let color = graphics::BLACK;
+                    draw_facing(ctx, &handicap, player_id, position, color)?;
+                }
+                _ => (),
+            }
+
+            let mut top = WINDOW_SIZE.1 / 2.0 + MARGIN;
+            if let Some(ticks) = game.time_left() {
+                let seconds = (ticks + TICKS_PER_SECOND - 1) / TICKS_PER_SECOND;
+                let clock = format!("{}:{:02}", seconds / 60, seconds % 60);
+                let clock = text(clock, SCORE_SIZE);
+                let (width, height) = clock.dimensions(ctx);
+                let position = (WINDOW_SIZE.0 - MARGIN - width as f32, top);
+                draw_facing(ctx, &clock, player_id, position, graphics::BLACK)?;
+                top += height as f32;
+            }
+
+            if self.longest_rally > 0 {
+                let rally = text(self.rally_text(), RALLY_SIZE);
+                let (width, _) = rally.dimensions(ctx);
+                let position = (WINDOW_SIZE.0 - MARGIN - width as f32, top);
+                draw_facing(ctx, &rally, player_id, position, graphics::BLACK)?;
+            }
+        }
+
+        if let Some((player_id, left)) = self.point {
+            let text = text("POINT".to_string(), POINT_SIZE);
+            let (width, _) = text.dimensions(ctx);
+            let position = (
+                (WINDOW_SIZE.0 - width as f32) / 2.0,
+                WINDOW_SIZE.1 * 3.0 / 4.0,
+            );
+            let alpha = left as f32 / POINT_TICKS as f32;
+            let color = Color::new(0.0, 0.0, 0.0, alpha);
+            draw_facing(ctx, &text, player_id, position, color)?;
+        }
+
+        Ok(())
+    }
+
+    // Returns how the length of the rally is shown, with the longest one
+    // if it's longer.
+    fn rally_text(&self) -> String {
+        if self.longest_rally > self.rally {
+            format!("Rally {} (longest {})", self.rally, self.longest_rally)
+        } else {
+            format!("Rally {}", self.rally)
+        }
+    }
+}
+
+/// Returns the score of the match as given player sees it, their own points
+/// first. With more than two players, it's how many lives they have left.
+/// A lone player sees their score, their best one if it was higher, and
+/// their lives.
+pub fn score(game: &Match, player_id: u8) -> String {
+    let lives = match game.lives_left(player_id) {
+        0 => "OUT".to_string(),
+        1 => "1 life".to_string(),
+        lives => format!("{} lives", lives),
+    };
+    if game.alone.is_some() {
+        return if game.best > game.score {
+            format!("{} (best {})  {}", game.score, game.best, lives)
+        } else {
+            format!("{}  {}", game.score, lives)
+        };
+    }
+    if game.players > 2 {
+        return lives;
+    }
+
+    let own = usize::from(player_id);
+    let other = 1 - own;
+    let points = format!("{} : {}", game.points[own], game.points[other]);
+    if game.rules.sets > 1 {
+        format!("{} ({} : {})", points, game.sets[own], game.sets[other])
+    } else {
+        points
+    }
+}
+
+pub fn text(content: String, size: f32) -> Text {
+    Text::new(TextFragment::new(content).scale(Scale::uniform(size)))
+}
+
+/// Draws the text so that it reads upright for given player. The position is
+/// where the top left corner of the text is for the second player, who sits
+/// by the bottom edge. For the first player, the text is turned around the
+/// center of the window. For the players by the left and the right edge,
+/// the text keeps its distance from their edge and from the middle of it,
+/// but stays within the edge.
+pub fn draw_facing(
+    ctx: &mut Context,
+    text: &Text,
+    player_id: u8,
+    (x, y): (f32, f32),
+    color: Color,
+) -> GameResult<()> {
+    let (width, height) = text.dimensions(ctx);
+    let side = Side::of(player_id);
+    let along = if side.is_vertical() {
+        (x - (WINDOW_SIZE.0 - WINDOW_SIZE.1) / 2.0)
+            .min(WINDOW_SIZE.1 - width as f32)
+            .max(0.0)
+    } else {
+        x
+    };
+    let distance = WINDOW_SIZE.1 - y - height as f32;
+    draw_at_edge(ctx, text, side, (along, distance), color)
+}
+
+/// Draws the text so that it reads upright for the player by given edge.
+/// The text starts at given coordinate along the edge, counted from the
+/// player's left, and its bottom is given distance away from the edge.
+pub fn draw_at_edge(
+    ctx: &mut Context,
+    text: &Text,
+    side: Side,
+    (along, distance): (f32, f32),
+    color: Color,
+) -> GameResult<()> {
+    let (_, height) = text.dimensions(ctx);
+    let across = distance + height as f32;
+    let (width, length) = WINDOW_SIZE;
+    let (rotation, (x, y)) = match side {
+        Side::Top => (PI, (width - along, across)),
+        Side::Bottom => (0.0, (along, length - across)),
+        Side::Left => (PI / 2.0, (across, along)),
+        Side::Right => (-PI / 2.0, (width - across, length - along)),
+    };
+    let param = DrawParam::default()
+        .color(color)
+        .dest(Point2::new(x, y))
+        .rotation(rotation);
+
+    graphics::draw(ctx, text, param)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Rules;
+
+    #[test]
+    fn test_point_fades_away() {
+        let mut scoreboard = Scoreboard::default();
+        scoreboard.point(1);
+        for _ in 1..POINT_TICKS {
+            scoreboard.tick();
+            assert_eq!(
+                Some(1),
+                scoreboard.point.map(|(player_id, _)| player_id)
+            );
+        }
+
+        scoreboard.tick();
+        assert_eq!(None, scoreboard.point);
+    }
+
+    #[test]
+    fn test_longest_rally_stays_on_record() {
+        let mut scoreboard = Scoreboard::default();
+        scoreboard.rally(7);
+        assert_eq!("Rally 7", scoreboard.rally_text());
+        scoreboard.rally(0);
+        scoreboard.rally(2);
+        assert_eq!("Rally 2 (longest 7)", scoreboard.rally_text());
+
+        scoreboard.start_over(vec!["KIDS".to_string()]);
+        assert_eq!("Rally 0 (longest 7)", scoreboard.rally_text());
+        assert_eq!(vec!["KIDS".to_string()], scoreboard.handicaps);
+    }
+
+    #[test]
+    fn test_score_shows_own_points_first() {
+        let mut game = Match::new(Rules::default(), 2);
+        game.point(0);
+        assert_eq!("1 : 0", score(&game, 0));
+        assert_eq!("0 : 1", score(&game, 1));
+
+        game.rules.sets = 3;
+        game.sets = [0, 2];
+        assert_eq!("1 : 0 (0 : 2)", score(&game, 0));
+
+        let mut game = Match::new(Rules::default(), 3);
+        game.concede(2);
+        assert_eq!("5 lives", score(&game, 0));
+        assert_eq!("4 lives", score(&game, 2));
+
+        let mut game = Match::single(Rules::default(), 1, 2);
+        game.set_score(12);
+        game.concede(1);
+        game.set_score(3);
+        assert_eq!("3 (best 12)  4 lives", score(&game, 1));
+    }
+}
@@ -0,0 +1,129 @@
+use super::TICKS_PER_SECOND;
+use std::env;
+use std::f32::consts::PI;
+
+/// How many seconds does the count down before a serve take by default.
+const DEFAULT_DELAY: f32 = 3.0;
+
+/// The ball is served at most this many radians away from heading straight
+/// towards the server.
+pub const MAX_SERVE_ANGLE: f32 = PI / 6.0;
+
+/// Who gets the ball served after a point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ServeRule {
+    /// The players take turns.
+    Alternate,
+
+    /// The player who lost the point.
+    Loser,
+
+    /// The player who won the point.
+    Winner,
+}
+
+impl ServeRule {
+    /// Reads the rule from `PONG_SERVE` env var, which is one of "alternate"
+    /// (default), "loser" or "winner".
+    pub fn from_env() -> Self {
+        match env::var("PONG_SERVE").as_ref().map(String::as_str) {
+            Err(_) | Ok("alternate") => ServeRule::Alternate,
+            Ok("loser") => ServeRule::Loser,
+            Ok("winner") => ServeRule::Winner,
+            Ok(other) => panic!("Unknown serve rule {} in PONG_SERVE", other),
+        }
+    }
+
+    /// Returns who gets the next serve given who got the last one, who let
+    /// the goal in and who is still in the match. With more than two
+    /// players nobody in particular wins a point, hence the winner rule
+    /// serves to the loser too.
+    pub fn next_server(self, server: u8, conceded: u8, players: &[u8]) -> u8 {
+        let loser = if players.contains(&conceded) {
+            conceded
+        } else {
+            next_after(conceded, players)
+        };
+        match self {
+            ServeRule::Alternate => next_after(server, players),
+            ServeRule::Winner if players.len() == 2 => {
+                next_after(loser, players)
+            }
+            ServeRule::Loser | ServeRule::Winner => loser,
+        }
+    }
+}
+
+/// Returns the player who follows given one in the order of ids among given
+/// players, going around to the first one after the last one.
+pub fn next_after(player_id: u8, players: &[u8]) -> u8 {
+    players
+        .iter()
+        .find(|other| **other > player_id)
+        .or_else(|| players.first())
+        .copied()
+        .unwrap_or(player_id)
+}
+
+/// The ball waits in the middle of the playfield before it's served.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Serve {
+    /// Which player is the ball going to be served to. They can also serve
+    /// early themselves.
+    pub server: u8,
+
+    /// How many ticks until the ball is served.
+    pub count_down: u32,
+}
+
+impl Serve {
+    /// Returns how many whole seconds are left, as shown to the players.
+    pub fn seconds_left(self) -> u32 {
+        (self.count_down + TICKS_PER_SECOND - 1) / TICKS_PER_SECOND
+    }
+}
+
+/// Reads how many ticks does the count down before a serve take from
+/// `PONG_SERVE_DELAY` env var in seconds, three by default.
+pub fn delay_from_env() -> u32 {
+    let seconds: f32 = env::var("PONG_SERVE_DELAY")
+        .map(|value| value.parse().expect("Invalid PONG_SERVE_DELAY"))
+        .unwrap_or(DEFAULT_DELAY);
+
+    (seconds * TICKS_PER_SECOND as f32).round() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_server() {
+        let two = &[0, 1];
+        assert_eq!(0, ServeRule::Alternate.next_server(1, 0, two));
+        assert_eq!(1, ServeRule::Alternate.next_server(0, 0, two));
+        assert_eq!(0, ServeRule::Loser.next_server(0, 0, two));
+        assert_eq!(1, ServeRule::Loser.next_server(1, 1, two));
+        assert_eq!(1, ServeRule::Winner.next_server(0, 0, two));
+        assert_eq!(0, ServeRule::Winner.next_server(0, 1, two));
+
+        // The third player is out, so the turn skips them.
+        let three = &[0, 1, 3];
+        assert_eq!(3, ServeRule::Alternate.next_server(1, 0, three));
+        assert_eq!(0, ServeRule::Alternate.next_server(3, 0, three));
+        assert_eq!(3, ServeRule::Loser.next_server(0, 2, three));
+        assert_eq!(1, ServeRule::Winner.next_server(0, 1, three));
+    }
+
+    #[test]
+    fn test_seconds_left() {
+        let serve = |count_down| Serve {
+            server: 0,
+            count_down,
+        };
+        assert_eq!(3, serve(3 * TICKS_PER_SECOND).seconds_left());
+        assert_eq!(3, serve(2 * TICKS_PER_SECOND + 1).seconds_left());
+        assert_eq!(1, serve(1).seconds_left());
+        assert_eq!(0, serve(0).seconds_left());
+    }
+}
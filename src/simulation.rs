@@ -0,0 +1,1549 @@
+use super::ball::Ball;
+use super::handicap::Handicap;
+use super::level::{Level, Obstacle};
+use super::multiball::{Multiball, Scoring};
+use super::paddle::{Paddle, Side, Track, FRONT_OFFSET};
+use super::physics::{Contact, Materials, Physics};
+use super::powerups::{self, Active, Effect, Item, PowerUps, ITEM_RADIUS};
+use super::rally::Rally;
+use super::serve::{self, Serve, ServeRule, MAX_SERVE_ANGLE};
+use super::solo::{self, Brick, Mode, SOLO_PLAYER};
+use super::WINDOW_SIZE;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::Ordering;
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::num::ParseFloatError;
+use std::path::Path;
+
+/// Marks the ticks of a replay in which the server asked for the serve.
+const SERVE: &str = "serve";
+
+/// Starts the line of a replay which says how the game was set up.
+const SETTINGS: &str = "settings ";
+
+/// Everything that moves in the game. The simulation doesn't know about the
+/// controllers, it only sees where each paddle wants to go. Given the same
+/// seed and the same inputs, a match always plays out the same.
+pub struct Simulation {
+    /// The balls in play. There's always at least one, which waits in the
+    /// middle for a serve if the rally is over.
+    pub balls: Vec<Ball>,
+
+    /// Player's paddles, one by each occupied edge of the window.
+    pub paddles: Vec<Paddle>,
+
+    /// The serve the ball is waiting for, if it's not in play.
+    pub serve: Option<Serve>,
+
+    /// Power-ups waiting on the playfield.
+    pub items: Vec<Item>,
+
+    /// Effects of the power-ups in force.
+    pub active: Vec<Active>,
+
+    /// Bricks of the wall in breakout which are still whole.
+    pub bricks: Vec<Brick>,
+
+    // Moves the ball and bounces it off the walls and the paddles.
+    physics: Physics,
+
+    settings: Settings,
+
+    // Whether each player is still in the match. The goals of those who
+    // have been knocked out are walled off.
+    playing: Vec<bool>,
+
+    // Who got the last serve.
+    server: u8,
+
+    // How many ticks has the current rally lasted.
+    rally: u32,
+
+    // How many times have the paddles hit a ball in the current rally.
+    hits: u32,
+
+    // How many balls have taken part in the current rally.
+    rally_balls: usize,
+
+    // How many bricks have the balls broken.
+    broken: u32,
+
+    // How many times as fast as usual do the balls go by the effects in
+    // force, before the handicaps.
+    pace: f32,
+
+    // How many ticks of play have there been in the match, which is where
+    // the moving obstacles are.
+    ticks: u32,
+
+    // All randomness of the game comes from here.
+    rng: StdRng,
+}
+
+/// How the game is set up. It must be the same for a replay to play out the
+/// same.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    /// How many players take part, two to four. They sit by the top, the
+    /// bottom, the left and the right edge in this order.
+    pub players: u8,
+
+    /// Whether four players play two against two. The first and the third
+    /// player sit by the top edge, the others by the bottom one. The first
+    /// two play at the back and the others at the front.
+    pub doubles: bool,
+
+    /// Whether the players play against each other or one plays alone.
+    pub mode: Mode,
+
+    pub materials: Materials,
+
+    /// Who gets the ball served after a point.
+    pub serve_rule: ServeRule,
+
+    /// How many ticks does the count down before a serve take.
+    pub serve_delay: u32,
+
+    /// When do more balls join a rally.
+    pub multiball: Multiball,
+
+    /// How do the balls speed up as a rally goes on.
+    pub rally: Rally,
+
+    /// How often do power-ups appear and how long do they last.
+    pub powerups: PowerUps,
+
+    /// The layout of the court.
+    pub level: Level,
+
+    /// Handicap of each player in the order of their ids. Players without
+    /// one play without a handicap.
+    pub handicaps: Vec<Handicap>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            players: 2,
+            doubles: false,
+            mode: Mode::Versus,
+            materials: Materials::default(),
+            serve_rule: ServeRule::Alternate,
+            serve_delay: 0,
+            multiball: Multiball::default(),
+            rally: Rally::default(),
+            powerups: PowerUps::default(),
+            level: Level::default(),
+            handicaps: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings from env vars. `PONG_DOUBLES=true` sets up a
+    /// doubles match of four players. A single-player mode leaves only the
+    /// seat of `SOLO_PLAYER` taken.
+    pub fn from_env() -> Self {
+        let mode = Mode::from_env();
+        let doubles = env::var("PONG_DOUBLES")
+            .map(|value| value.parse().expect("Invalid PONG_DOUBLES"))
+            .unwrap_or(false)
+            && !mode.is_solo();
+        let players = if mode.is_solo() {
+            2
+        } else if doubles {
+            4
+        } else {
+            players_from_env()
+        };
+        Self {
+            players,
+            doubles,
+            mode,
+            materials: Materials::from_env(),
+            serve_rule: ServeRule::from_env(),
+            serve_delay: serve::delay_from_env(),
+            multiball: Multiball::from_env(),
+            rally: Rally::from_env(mode),
+            powerups: PowerUps::from_env(),
+            level: Level::from_env(),
+            handicaps: (0..players).map(Handicap::from_env).collect(),
+        }
+    }
+
+    /// Returns the handicap of given player. The balls heading towards a
+    /// doubles team go by the handicap of its back player.
+    pub fn handicap(&self, player_id: u8) -> Handicap {
+        self.handicaps
+            .get(usize::from(player_id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// How many teams play against each other. Each player is a team of
+    /// their own, unless they play doubles.
+    pub fn teams(&self) -> u8 {
+        if self.doubles {
+            2
+        } else {
+            self.players
+        }
+    }
+
+    /// Returns the team of given player, which is the id of the player who
+    /// plays at the back for it. A team defends the goal of the edge of the
+    /// same id.
+    pub fn team(&self, player_id: u8) -> u8 {
+        player_id % self.teams()
+    }
+}
+
+/// Reads how many players take part from `PONG_PLAYERS` env var, two by
+/// default.
+pub fn players_from_env() -> u8 {
+    let players = env::var("PONG_PLAYERS")
+        .map(|value| value.parse().expect("Invalid PONG_PLAYERS"))
+        .unwrap_or(2);
+    assert!(
+        players >= 2 && players <= 4,
+        "PONG_PLAYERS must be 2, 3 or 4"
+    );
+    players
+}
+
+/// What the players did in one tick.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Inputs {
+    /// Where does each player's paddle want to go, if anywhere, in the
+    /// order of their ids.
+    pub targets: Vec<Option<f32>>,
+
+    /// Whether the player the ball is going to be served to wants it served
+    /// right away.
+    pub serve: bool,
+
+    /// Changes to the match since the last tick, which the tick makes before
+    /// anything moves.
+    pub changes: Vec<Change>,
+}
+
+/// A change to the match which doesn't come from the paddles, e.g. when the
+/// operator takes a point back or switches a handicap. Changes go through
+/// the inputs, so that a replay makes them at the same tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Change {
+    /// The player with given id is back in the match if true, or knocked
+    /// out, see `Simulation::set_playing`.
+    Playing(u8, bool),
+
+    /// The player with given id plays with a new handicap, see
+    /// `Simulation::set_handicap`.
+    Handicap(u8, Handicap),
+}
+
+impl fmt::Display for Change {
+    /// Writes the change as a word of a replay line, e.g. "out:2" or
+    /// "handicap:1:width=160,speed=0.7,points=0,steady".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Playing(player_id, true) => write!(f, "in:{}", player_id),
+            Change::Playing(player_id, false) => {
+                write!(f, "out:{}", player_id)
+            }
+            Change::Handicap(player_id, handicap) => {
+                write!(f, "handicap:{}:{}", player_id, handicap)
+            }
+        }
+    }
+}
+
+impl Change {
+    /// Reads a change written by `fmt`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.splitn(3, ':');
+        let kind = parts.next()?;
+        let player_id = parts.next()?.parse().ok()?;
+        match (kind, parts.next()) {
+            ("in", None) => Some(Change::Playing(player_id, true)),
+            ("out", None) => Some(Change::Playing(player_id, false)),
+            ("handicap", Some(handicap)) => {
+                Some(Change::Handicap(player_id, Handicap::parse(handicap)?))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Simulation {
+    /// Sets up a new match with given seed. The first serve goes to a random
+    /// player. In a single-player game, the seats other than `SOLO_PLAYER`
+    /// are walled off and the serve goes to the lone player.
+    pub fn new(seed: u64, settings: Settings) -> Self {
+        let level = &settings.level;
+        let paddles: Vec<_> = (0..settings.players)
+            .map(|player_id| {
+                let team = settings.team(player_id);
+                let front = Track {
+                    offset: FRONT_OFFSET,
+                    ..Default::default()
+                };
+                let track = match level.tracks.get(usize::from(player_id)) {
+                    Some(track) => *track,
+                    None if team != player_id => front,
+                    None => Track::default(),
+                };
+                let mut paddle = Paddle::new(player_id)
+                    .with_side(Side::of(team))
+                    .with_track(track);
+                paddle.width = settings.handicap(player_id).paddle_width;
+                paddle
+            })
+            .collect();
+        let physics =
+            Physics::new(settings.materials, &paddles, &level.obstacles);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let server = rng.gen_range(0, settings.teams());
+        let mut simulation = Self {
+            physics,
+            server,
+            rally: 0,
+            hits: 0,
+            rally_balls: 1,
+            broken: 0,
+            pace: 1.0,
+            ticks: 0,
+            serve: Some(Serve {
+                server,
+                count_down: settings.serve_delay,
+            }),
+            playing: vec![true; paddles.len()],
+            settings,
+            balls: Vec::new(),
+            paddles,
+            items: Vec::new(),
+            active: Vec::new(),
+            bricks: Vec::new(),
+            rng,
+        };
+
+        if simulation.settings.mode.is_solo() {
+            for player_id in 0..simulation.settings.players {
+                simulation.set_playing(player_id, player_id == SOLO_PLAYER);
+            }
+        }
+        if simulation.settings.mode == Mode::Breakout {
+            for brick in solo::wall() {
+                simulation.physics.add_brick(&brick);
+                simulation.bricks.push(brick);
+            }
+        }
+
+        let ball = simulation.waiting_ball();
+        simulation.physics.add_ball(&ball);
+        simulation.balls.push(ball);
+        simulation
+    }
+
+    /// Returns the obstacles of the level along with where they are now.
+    pub fn obstacles(
+        &self,
+    ) -> impl Iterator<Item = (&Obstacle, (f32, f32))> + '_ {
+        self.settings
+            .level
+            .obstacles
+            .iter()
+            .map(move |obstacle| (obstacle, obstacle.center_at(self.ticks)))
+    }
+
+    /// Returns the ball the player should mind the most. That's the ball
+    /// closest to their paddle of those heading towards it or, if no ball
+    /// is, the closest ball.
+    pub fn threat(&self, player_id: u8) -> &Ball {
+        let paddle = &self.paddles[usize::from(player_id)];
+        let side = paddle.side;
+        let urgency = |ball: &Ball| {
+            let distance =
+                paddle.ball_line(ball.radius) - side.across(ball.center);
+            let heading = distance * side.across(ball.direction()) > 0.0;
+            let behind = if heading {
+                0.0
+            } else {
+                side.across(WINDOW_SIZE)
+            };
+            distance.abs() + behind
+        };
+
+        self.balls
+            .iter()
+            .min_by(|a, b| {
+                urgency(a)
+                    .partial_cmp(&urgency(b))
+                    .unwrap_or(Ordering::Equal)
+            })
+            .expect("There's always a ball")
+    }
+
+    /// Whether the player's controls move their paddle the other way.
+    pub fn reversed(&self, player_id: u8) -> bool {
+        self.active.iter().any(|active| {
+            active.effect == Effect::Reverse && active.player_id == player_id
+        })
+    }
+
+    /// Returns how many goals has each player let in.
+    pub fn conceded(&self) -> Vec<usize> {
+        self.paddles.iter().map(|paddle| paddle.deaths).collect()
+    }
+
+    /// Returns the score of the lone player of a single-player game, which
+    /// is how many bricks they have broken in breakout or how many times in
+    /// a row they have returned the ball in squash.
+    pub fn score(&self) -> u32 {
+        match self.settings.mode {
+            Mode::Versus => 0,
+            Mode::Breakout => self.broken,
+            Mode::Squash => self.hits,
+        }
+    }
+
+    /// Returns how many times in a row have the paddles hit the balls in the
+    /// current rally, or in the last one while the ball waits for a serve.
+    pub fn rally_length(&self) -> u32 {
+        self.hits
+    }
+
+    /// Whether all the bricks of the wall in breakout are broken.
+    pub fn is_cleared(&self) -> bool {
+        self.settings.mode == Mode::Breakout && self.bricks.is_empty()
+    }
+
+    /// Returns the team of given player, see `Settings::team`.
+    pub fn team(&self, player_id: u8) -> u8 {
+        self.settings.team(player_id)
+    }
+
+    /// Whether the player is still in the match.
+    pub fn is_playing(&self, player_id: u8) -> bool {
+        self.playing[usize::from(player_id)]
+    }
+
+    /// Changes the handicap of given player. Their paddle and the balls
+    /// heading towards them follow it right away.
+    pub fn set_handicap(&mut self, player_id: u8, handicap: Handicap) {
+        let p = usize::from(player_id);
+        let handicaps = &mut self.settings.handicaps;
+        if handicaps.len() <= p {
+            handicaps.resize(p + 1, Handicap::default());
+        }
+        handicaps[p] = handicap;
+        self.apply_effects();
+    }
+
+    /// Knocks the player out of the match, which takes their paddle away and
+    /// walls their goal off, or brings them back in. A serve waiting for a
+    /// player who is out goes to the next player instead. Players of doubles
+    /// aren't knocked out.
+    pub fn set_playing(&mut self, player_id: u8, playing: bool) {
+        let p = usize::from(player_id);
+        if self.playing[p] == playing {
+            return;
+        }
+
+        self.playing[p] = playing;
+        if playing {
+            self.physics.open_goal(p, &self.paddles[p]);
+        } else {
+            self.physics.close_goal(p, self.paddles[p].side);
+        }
+
+        let teams = self.teams_in_play();
+        if let Some(serve) = self.serve.as_mut() {
+            if !teams.contains(&serve.server) && !teams.is_empty() {
+                serve.server = serve::next_after(serve.server, &teams);
+            }
+        }
+    }
+
+    /// Makes the changes to the match, then moves the paddles and the balls
+    /// by one tick. Returns the ids of the players who let a goal in, one
+    /// for each point.
+    pub fn step(&mut self, inputs: &Inputs) -> Vec<u8> {
+        for change in &inputs.changes {
+            match *change {
+                Change::Playing(player_id, playing) => {
+                    self.set_playing(player_id, playing)
+                }
+                Change::Handicap(player_id, handicap) => {
+                    self.set_handicap(player_id, handicap)
+                }
+            }
+        }
+
+        for (paddle, target) in self.paddles.iter_mut().zip(&inputs.targets) {
+            paddle.previous_x = paddle.x;
+            if let Some(x) = target {
+                paddle.move_to(*x);
+            }
+            paddle.track_velocity();
+        }
+
+        // The ball waits in the middle until the count down ends or until
+        // the server asks for it.
+        if let Some(serve) = self.serve.as_mut() {
+            self.balls[0].previous_center = self.balls[0].center;
+            if serve.count_down > 0 && !inputs.serve {
+                serve.count_down -= 1;
+                return Vec::new();
+            }
+
+            let angle = self.rng.gen_range(-MAX_SERVE_ANGLE, MAX_SERVE_ANGLE);
+            self.balls[0].serve(serve.server, angle);
+            self.server = serve.server;
+            self.serve = None;
+            self.rally = 0;
+            self.hits = 0;
+            self.rally_balls = 1;
+        }
+
+        // Moves the balls at the pace of the rally, unless they head towards
+        // a player whose handicap keeps them steady.
+        self.rally += 1;
+        for ball in self.balls.iter_mut() {
+            ball.tick();
+        }
+        self.pace_balls();
+        // Where a ball leaves a paddle to is up to where it hit the paddle.
+        // A brick which a ball hits breaks.
+        let mut hit = false;
+        let mut broken = Vec::new();
+        let obstacles = &self.settings.level.obstacles;
+        let contacts = self.physics.step(
+            &mut self.balls,
+            &self.paddles,
+            obstacles,
+            self.ticks,
+        );
+        self.ticks += 1;
+        for (index, contact) in contacts {
+            let ball = &mut self.balls[index];
+            let kick = match contact {
+                Contact::Wall | Contact::Ball => 0.0,
+                Contact::Paddle(player_id) => {
+                    ball.aim(&self.paddles[player_id as usize]);
+                    self.hits += 1;
+                    hit = true;
+                    0.0
+                }
+                Contact::Obstacle(obstacle) => obstacles[obstacle].bumper,
+                Contact::Brick(brick) => {
+                    broken.push(brick);
+                    0.0
+                }
+            };
+            if !self.handicap_ahead(&self.balls[index]).steady {
+                self.balls[index].kick(kick);
+            }
+            let noise = self.settings.materials.noise;
+            self.balls[index].bounce(noise, &mut self.rng);
+        }
+
+        self.break_bricks(broken);
+
+        self.spawn_item();
+        self.collect_items();
+        self.wear_off();
+
+        let hits = if hit { Some(self.hits) } else { None };
+        if self
+            .settings
+            .multiball
+            .spawns(self.rally, hits, self.rally_balls)
+        {
+            self.spawn();
+        }
+
+        let conceded = self.take_out_scored();
+
+        // A ball goes back to the middle once the last one is out and waits
+        // for the next serve.
+        if let Some(player_id) = conceded.last() {
+            if self.balls.is_empty() {
+                let ball = self.waiting_ball();
+                self.physics.add_ball(&ball);
+                self.balls.push(ball);
+                let server = self.settings.serve_rule.next_server(
+                    self.server,
+                    *player_id,
+                    &self.teams_in_play(),
+                );
+                self.serve = Some(Serve {
+                    server,
+                    count_down: self.settings.serve_delay,
+                });
+            }
+        }
+
+        conceded
+    }
+
+    // Returns the handicap of the team the ball heads towards the most
+    // directly, if it heads towards any team in the match.
+    fn handicap_ahead(&self, ball: &Ball) -> Handicap {
+        let direction = ball.direction();
+        let approach = |team: u8| {
+            -Side::of(team).across(direction) * Side::of(team).away()
+        };
+        self.teams_in_play()
+            .into_iter()
+            .filter(|team| approach(*team) > 0.0)
+            .max_by(|a, b| {
+                approach(*a)
+                    .partial_cmp(&approach(*b))
+                    .unwrap_or(Ordering::Equal)
+            })
+            .map_or_else(Handicap::default, |team| self.settings.handicap(team))
+    }
+
+    // Sets the pace of each ball, see `pace_of`.
+    fn pace_balls(&mut self) {
+        let paces: Vec<_> =
+            self.balls.iter().map(|ball| self.pace_of(ball)).collect();
+        for (ball, pace) in self.balls.iter_mut().zip(paces) {
+            ball.pace = pace;
+        }
+    }
+
+    // Returns the pace of the ball by the effects in force, by the length of
+    // the rally and by the handicap of the team it heads towards. Balls
+    // heading towards a steady team don't speed up with the rally.
+    fn pace_of(&self, ball: &Ball) -> f32 {
+        let handicap = self.handicap_ahead(ball);
+        let rally = if handicap.steady {
+            1.0
+        } else {
+            self.settings.rally.pace(self.hits)
+        };
+        self.pace * rally * handicap.ball_speed
+    }
+
+    // Returns the ids of the teams which are still in the match.
+    fn teams_in_play(&self) -> Vec<u8> {
+        (0..self.settings.teams())
+            .filter(|team| self.is_playing(*team))
+            .collect()
+    }
+
+    // Puts a new ball where balls wait for a serve in the level, which is
+    // picked at random if there are several such places.
+    fn waiting_ball(&mut self) -> Ball {
+        let spawns = self.settings.level.spawns.len();
+        let random = if spawns > 1 {
+            self.rng.gen_range(0, spawns)
+        } else {
+            0
+        };
+
+        let mut ball = Ball::default();
+        ball.center = self.settings.level.spawn(random);
+        ball.previous_center = ball.center;
+        ball
+    }
+
+    // Serves another ball from where balls wait to a random team which is
+    // still in the match.
+    fn spawn(&mut self) {
+        let mut ball = self.waiting_ball();
+        let teams = self.teams_in_play();
+        let player_id = teams[self.rng.gen_range(0, teams.len())];
+        let angle = self.rng.gen_range(-MAX_SERVE_ANGLE, MAX_SERVE_ANGLE);
+        ball.serve(player_id, angle);
+        ball.pace = self.pace_of(&ball);
+        self.physics.add_ball(&ball);
+        self.balls.push(ball);
+        self.rally_balls += 1;
+    }
+
+    // Takes the bricks with given indices out of the wall.
+    fn break_bricks(&mut self, mut indices: Vec<usize>) {
+        indices.sort_unstable();
+        indices.dedup();
+        for index in indices.into_iter().rev() {
+            self.bricks.remove(index);
+            self.physics.remove_brick(index);
+            self.broken += 1;
+        }
+    }
+
+    // Ages the power-ups on the playfield and puts a new one at a random
+    // place in the middle of the playfield when it's time.
+    fn spawn_item(&mut self) {
+        for item in self.items.iter_mut() {
+            item.ticks_left = item.ticks_left.saturating_sub(1);
+        }
+        self.items.retain(|item| item.ticks_left > 0);
+
+        let powerups = self.settings.powerups;
+        let due = powerups
+            .every
+            .map_or(false, |every| every > 0 && self.rally % every == 0);
+        if !due || self.items.len() >= powerups.max_items {
+            return;
+        }
+
+        let margin = ITEM_RADIUS * 2.0;
+        let x = self.rng.gen_range(margin, WINDOW_SIZE.0 - margin);
+        let y = self
+            .rng
+            .gen_range(WINDOW_SIZE.1 * 0.25, WINDOW_SIZE.1 * 0.75);
+        let effect = Effect::ALL[self.rng.gen_range(0, Effect::ALL.len())];
+        self.items.push(Item {
+            effect,
+            center: (x, y),
+            ticks_left: powerups.lifetime,
+        });
+    }
+
+    // Activates the power-ups the balls pass through. They go to whoever
+    // has hit the ball last, balls nobody has hit yet pass through them.
+    fn collect_items(&mut self) {
+        let mut index = 0;
+        while index < self.items.len() {
+            let player_id = self
+                .balls
+                .iter()
+                .filter(|ball| {
+                    self.items[index].touches(ball.center, ball.radius)
+                })
+                .filter_map(|ball| ball.last_hit)
+                .next();
+            match player_id {
+                Some(player_id) => {
+                    let item = self.items.remove(index);
+                    self.activate(item.effect, player_id);
+                }
+                None => index += 1,
+            }
+        }
+    }
+
+    // Puts the effect of a power-up the player has got in force.
+    fn activate(&mut self, effect: Effect, player_id: u8) {
+        if effect == Effect::ExtraBall {
+            self.spawn();
+            return;
+        }
+
+        // An effect which hurts the other team goes to one of the others at
+        // random if there are several.
+        let team = self.settings.team(player_id);
+        let player_id = if effect.hurts_other() {
+            let others: Vec<_> = self
+                .teams_in_play()
+                .into_iter()
+                .filter(|other| *other != team)
+                .collect();
+            match others.len() {
+                0 => return,
+                1 => others[0],
+                n => others[self.rng.gen_range(0, n)],
+            }
+        } else {
+            player_id
+        };
+        // Getting the same effect again starts its time over.
+        self.active.retain(|active| {
+            active.effect != effect || active.player_id != player_id
+        });
+        self.active.push(Active {
+            effect,
+            player_id,
+            ticks_left: self.settings.powerups.duration,
+        });
+        self.apply_effects();
+    }
+
+    // Wears off the effects of the power-ups whose time is up.
+    fn wear_off(&mut self) {
+        if self.active.is_empty() {
+            return;
+        }
+
+        for active in self.active.iter_mut() {
+            active.ticks_left = active.ticks_left.saturating_sub(1);
+        }
+        self.active.retain(|active| active.ticks_left > 0);
+        self.apply_effects();
+    }
+
+    // Sets the widths of the paddles and the pace of the balls by the
+    // effects in force.
+    fn apply_effects(&mut self) {
+        let mut pace = 1.0;
+        for paddle in self.paddles.iter_mut() {
+            paddle.width =
+                self.settings.handicap(paddle.player_id).paddle_width;
+        }
+        for active in &self.active {
+            let paddle = &mut self.paddles[usize::from(active.player_id)];
+            match active.effect {
+                Effect::Grow => paddle.width *= powerups::GROW_FACTOR,
+                Effect::Shrink => paddle.width *= powerups::SHRINK_FACTOR,
+                Effect::SpeedUp => pace *= powerups::SPEED_UP_FACTOR,
+                Effect::SlowDown => pace *= powerups::SLOW_DOWN_FACTOR,
+                _ => (),
+            }
+        }
+
+        for paddle in self.paddles.iter_mut() {
+            let x = paddle.x;
+            paddle.move_to(x);
+        }
+        self.pace = pace;
+        self.pace_balls();
+    }
+
+    // Takes the balls which have left the playfield out of the game. A ball
+    // which gets past a shield bounces back and uses the shield up, as does
+    // a ball which slips out by an edge where nobody plays. Returns who let
+    // the goals in by the multiball scoring.
+    fn take_out_scored(&mut self) -> Vec<u8> {
+        let mut conceded = Vec::new();
+        let mut index = 0;
+        while index < self.balls.len() {
+            let side = match self.balls[index].left_through() {
+                Some(side) => side,
+                None => {
+                    index += 1;
+                    continue;
+                }
+            };
+
+            let loser = side.player_id();
+            let open = loser < self.settings.teams() && self.is_playing(loser);
+            let settings = &self.settings;
+            let shield = self.active.iter().position(|active| {
+                active.effect == Effect::Shield
+                    && settings.team(active.player_id) == loser
+            });
+            if let (true, Some(shield)) = (open, shield) {
+                self.active.remove(shield);
+            }
+            if shield.is_some() || !open {
+                self.balls[index].rebound(side);
+                index += 1;
+                continue;
+            }
+
+            self.balls.remove(index);
+            self.physics.remove_ball(index);
+            let scores = self.settings.multiball.scoring == Scoring::EachBall
+                || self.balls.is_empty();
+            if scores {
+                self.paddles[usize::from(loser)].deaths += 1;
+                conceded.push(loser);
+            }
+        }
+
+        conceded
+    }
+}
+
+/// Seed, settings and inputs of a match, which is all we need to play it
+/// again.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub settings: Settings,
+    pub inputs: Vec<Inputs>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, settings: Settings) -> Self {
+        Self {
+            seed,
+            settings,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Writes the replay as text. The first line is the seed and the second
+    /// one the settings. Each next line are the targets of all paddles in
+    /// one tick, "-" if there's none, followed by "serve" if the server
+    /// asked for the serve and by the changes to the match.
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{}", self.seed)?;
+        writeln!(writer, "{}{:?}", SETTINGS, self.settings)?;
+        for inputs in &self.inputs {
+            let targets: Vec<_> =
+                inputs.targets.iter().map(|x| target_to_str(*x)).collect();
+            write!(writer, "{}", targets.join(" "))?;
+            if inputs.serve {
+                write!(writer, " {}", SERVE)?;
+            }
+            for change in &inputs.changes {
+                write!(writer, " {}", change)?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Saves the replay into a file.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Loads a replay saved by `save`, see `read`.
+    pub fn load(
+        path: impl AsRef<Path>,
+        settings: &Settings,
+    ) -> io::Result<Self> {
+        Self::read(BufReader::new(File::open(path)?), settings)
+    }
+
+    /// Reads the replay written by `write`. A replay plays out the same only
+    /// with the settings it was recorded with, so a replay recorded with
+    /// other settings than given ones is refused.
+    pub fn read(reader: impl BufRead, settings: &Settings) -> io::Result<Self> {
+        let invalid = |line: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid replay line: {}", line),
+            )
+        };
+
+        let mut lines = reader.lines();
+        let mut next_line =
+            || lines.next().unwrap_or_else(|| Ok(String::new()));
+        let seed = next_line()?;
+        let seed = seed.trim().parse().map_err(|_| invalid(&seed))?;
+        let recorded = next_line()?;
+        if recorded != format!("{}{:?}", SETTINGS, settings) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Replay recorded with other settings, {} instead of {:?}",
+                    recorded, settings
+                ),
+            ));
+        }
+
+        let mut replay = Replay::new(seed, settings.clone());
+        for line in lines {
+            let line = line?;
+            let mut words = line.split_whitespace().peekable();
+            let mut targets = Vec::new();
+            while let Some(target) =
+                words.peek().and_then(|word| target_from_str(word).ok())
+            {
+                targets.push(target);
+                words.next();
+            }
+            let serve = words.peek() == Some(&SERVE);
+            if serve {
+                words.next();
+            }
+            let changes: Vec<_> = words
+                .map(Change::parse)
+                .collect::<Option<_>>()
+                .ok_or_else(|| invalid(&line))?;
+
+            if targets.len() != usize::from(settings.players) {
+                return Err(invalid(&line));
+            }
+            replay.inputs.push(Inputs {
+                targets,
+                serve,
+                changes,
+            });
+        }
+
+        Ok(replay)
+    }
+}
+
+fn target_to_str(target: Option<f32>) -> String {
+    target.map_or_else(|| "-".to_string(), |x| x.to_string())
+}
+
+fn target_from_str(target: &str) -> Result<Option<f32>, ParseFloatError> {
+    match target {
+        "-" => Ok(None),
+        x => x.parse().map(Some),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handicap::KIDS;
+    use crate::paddle::PADDLE_SIZE;
+    use crate::rally::Curve;
+    use crate::solo::SQUASH_SPEED_UP;
+
+    fn settings() -> Settings {
+        Settings {
+            serve_delay: 30,
+            ..Default::default()
+        }
+    }
+
+    // Plays the whole match again and returns its final state.
+    fn play(replay: &Replay) -> Simulation {
+        let mut simulation =
+            Simulation::new(replay.seed, replay.settings.clone());
+        for inputs in &replay.inputs {
+            simulation.step(inputs);
+        }
+        simulation
+    }
+
+    // Plays a rally where both paddles swing from side to side.
+    fn replay(seed: u64) -> Replay {
+        let mut replay = Replay::new(seed, settings());
+        for tick in 0..3000 {
+            let swing = (tick as f32 / 40.0).sin();
+            replay.inputs.push(Inputs {
+                targets: vec![
+                    Some(600.0 + swing * 500.0),
+                    if tick % 3 == 0 {
+                        None
+                    } else {
+                        Some(600.0 - swing * 400.0)
+                    },
+                ],
+                serve: tick % 500 == 0,
+                changes: Vec::new(),
+            });
+        }
+        replay
+    }
+
+    #[test]
+    fn test_same_seed_and_inputs_play_out_the_same() {
+        let a = play(&replay(7));
+        let b = play(&replay(7));
+        assert_eq!(a.balls, b.balls);
+        assert_eq!(a.paddles, b.paddles);
+        assert_eq!(
+            a.balls[0].center.0.to_bits(),
+            b.balls[0].center.0.to_bits()
+        );
+    }
+
+    #[test]
+    fn test_different_seed_plays_out_differently() {
+        assert_ne!(play(&replay(7)).balls, play(&replay(8)).balls);
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let replay = replay(42);
+        let mut text = Vec::new();
+        replay.write(&mut text).unwrap();
+        let read = Replay::read(&text[..], &settings()).unwrap();
+        assert_eq!(replay, read);
+        assert_eq!(play(&replay).balls, play(&read).balls);
+
+        // A replay doesn't play with other settings than its own.
+        let other = Settings {
+            players: 3,
+            ..settings()
+        };
+        assert!(Replay::read(&text[..], &other).is_err());
+    }
+
+    #[test]
+    fn test_read_malformed() {
+        let read = |lines: &str| {
+            let text = format!("1\n{}{:?}\n{}", SETTINGS, settings(), lines);
+            Replay::read(text.as_bytes(), &settings())
+        };
+        assert!(Replay::read(&b"seed\n"[..], &settings()).is_err());
+        assert!(Replay::read(&b"1\n- 2.5\n"[..], &settings()).is_err());
+        assert!(read("1.0\n").is_err());
+        assert!(read("1.0 x\n").is_err());
+        assert!(read("- serve\n").is_err());
+        assert!(read("- 2.5 3.5\n").is_err());
+        assert!(read("- 2.5 out\n").is_err());
+        assert!(read("- 2.5\n").is_ok());
+        assert!(read("- 2.5 serve\n").unwrap().inputs[0].serve);
+        let changes = &read("- 2.5 out:1 handicap:0:kids\n").unwrap().inputs[0];
+        assert_eq!(
+            vec![Change::Playing(1, false), Change::Handicap(0, KIDS)],
+            changes.changes
+        );
+    }
+
+    #[test]
+    fn test_replay_changes() {
+        let settings = Settings {
+            players: 3,
+            ..settings()
+        };
+        let mut replay = Replay::new(5, settings);
+        for tick in 0..2000 {
+            let swing = (tick as f32 / 30.0).sin();
+            // The third player is knocked out and then the point is taken
+            // back, which brings them in again. The second player switches
+            // their handicap on the way.
+            let changes = match tick {
+                400 => vec![Change::Playing(2, false)],
+                700 => vec![Change::Playing(2, true)],
+                900 => vec![Change::Handicap(1, KIDS)],
+                _ => Vec::new(),
+            };
+            replay.inputs.push(Inputs {
+                targets: vec![Some(600.0 + swing * 500.0); 3],
+                serve: tick % 300 == 0,
+                changes,
+            });
+        }
+
+        let mut text = Vec::new();
+        replay.write(&mut text).unwrap();
+        let read = Replay::read(&text[..], &replay.settings).unwrap();
+        assert_eq!(replay, read);
+        let (a, b) = (play(&replay), play(&read));
+        assert_eq!(a.balls, b.balls);
+        assert_eq!(a.paddles, b.paddles);
+        assert_eq!(a.conceded(), b.conceded());
+        assert!(b.is_playing(2));
+        assert!((b.paddles[1].width - KIDS.paddle_width).abs() < 0.01);
+
+        // Without the changes, the match plays out differently.
+        for inputs in replay.inputs.iter_mut() {
+            inputs.changes.clear();
+        }
+        assert_ne!(play(&replay).paddles, b.paddles);
+    }
+
+    #[test]
+    fn test_serve_after_count_down_or_on_request() {
+        let mut simulation = Simulation::new(1, settings());
+        let server = simulation.serve.unwrap().server;
+        let wait = Inputs {
+            targets: vec![None; 2],
+            serve: false,
+            changes: Vec::new(),
+        };
+        for _ in 0..30 {
+            simulation.step(&wait);
+            assert_eq!(simulation.balls[0].center, Ball::default().center);
+        }
+        simulation.step(&wait);
+        assert_eq!(None, simulation.serve);
+        let towards_server = if server == 0 { -1.0 } else { 1.0 };
+        assert!(simulation.balls[0].direction().1 * towards_server > 0.0);
+
+        // Both paddles are out of the way, so the ball leaves the playfield.
+        // The alternate rule gives the next serve to the other player and
+        // they want it right away.
+        let aside = Inputs {
+            targets: vec![Some(0.0); 2],
+            serve: false,
+            changes: Vec::new(),
+        };
+        while simulation.serve.is_none() {
+            simulation.step(&aside);
+        }
+        assert_eq!(1 - server, simulation.serve.unwrap().server);
+        let mut conceded = vec![0, 0];
+        conceded[server as usize] = 1;
+        assert_eq!(conceded, simulation.conceded());
+        simulation.step(&Inputs {
+            targets: vec![None; 2],
+            serve: true,
+            changes: Vec::new(),
+        });
+        assert_eq!(None, simulation.serve);
+    }
+
+    // Serves right away and lets more balls join every second, while both
+    // paddles stay out of the way.
+    fn multiball(scoring: Scoring) -> (Simulation, Vec<u8>) {
+        let settings = Settings {
+            multiball: Multiball {
+                every: Some(60),
+                scoring,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(3, settings);
+        let aside = Inputs {
+            targets: vec![Some(0.0); 2],
+            serve: false,
+            changes: Vec::new(),
+        };
+
+        let mut scored = simulation.step(&aside);
+        let mut most_balls = 0;
+        while simulation.serve.is_none() {
+            scored.extend(simulation.step(&aside));
+            most_balls = most_balls.max(simulation.balls.len());
+        }
+
+        assert!(most_balls > 1);
+        assert_eq!(1, simulation.balls.len());
+        (simulation, scored)
+    }
+
+    #[test]
+    fn test_each_ball_scores() {
+        let (simulation, scored) = multiball(Scoring::EachBall);
+        assert!(scored.len() > 1);
+        assert_eq!(scored.len(), simulation.conceded().iter().sum());
+    }
+
+    #[test]
+    fn test_last_ball_scores() {
+        let (simulation, scored) = multiball(Scoring::LastBall);
+        assert_eq!(1, scored.len());
+        assert_eq!(1, simulation.conceded().iter().sum::<usize>());
+    }
+
+    #[test]
+    fn test_threat() {
+        let mut simulation = Simulation::new(1, settings());
+        let mut lower = Ball::default();
+        lower.follow((300.0, 200.0), (0.0, 300.0));
+        let mut upper = Ball::default();
+        upper.follow((600.0, 100.0), (0.0, 300.0));
+        simulation.balls = vec![lower.clone(), upper.clone()];
+
+        // Both balls head towards the second player and the lower one is
+        // closer to them. Neither heads towards the first player, who minds
+        // the closer one.
+        assert_eq!(&lower, simulation.threat(1));
+        assert_eq!(&upper, simulation.threat(0));
+    }
+
+    #[test]
+    fn test_powerups_go_to_last_hitter_and_wear_off() {
+        let settings = Settings {
+            powerups: PowerUps {
+                duration: 5,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(1, settings);
+        let wait = Inputs {
+            targets: vec![None; 2],
+            serve: false,
+            changes: Vec::new(),
+        };
+        simulation.step(&wait);
+
+        // Nobody has hit the ball yet, so it passes through the power-up.
+        let item = |effect| Item {
+            effect,
+            center: simulation.balls[0].center,
+            ticks_left: 100,
+        };
+        simulation.items = vec![item(Effect::Grow), item(Effect::Shrink)];
+        simulation.step(&wait);
+        assert_eq!(2, simulation.items.len());
+
+        simulation.balls[0].last_hit = Some(1);
+        simulation.step(&wait);
+        assert!(simulation.items.is_empty());
+        let widths = |simulation: &Simulation| {
+            [simulation.paddles[0].width, simulation.paddles[1].width]
+        };
+        assert_eq!(
+            [
+                PADDLE_SIZE.0 * powerups::SHRINK_FACTOR,
+                PADDLE_SIZE.0 * powerups::GROW_FACTOR
+            ],
+            widths(&simulation)
+        );
+
+        for _ in 0..5 {
+            simulation.step(&wait);
+        }
+        assert!(simulation.active.is_empty());
+        assert_eq!([PADDLE_SIZE.0; 2], widths(&simulation));
+    }
+
+    #[test]
+    fn test_shield_bounces_ball_back() {
+        let mut simulation = Simulation::new(1, Settings::default());
+        let shield = |player_id| Active {
+            effect: Effect::Shield,
+            player_id,
+            ticks_left: 100_000,
+        };
+        simulation.active = vec![shield(0), shield(1)];
+        let aside = Inputs {
+            targets: vec![Some(0.0); 2],
+            serve: false,
+            changes: Vec::new(),
+        };
+
+        // Each shield sends the ball back once, then the next miss scores.
+        simulation.step(&aside);
+        while !simulation.active.is_empty() {
+            simulation.step(&aside);
+            assert_eq!(None, simulation.serve);
+        }
+        assert_eq!(vec![0, 0], simulation.conceded());
+        while simulation.serve.is_none() {
+            simulation.step(&aside);
+        }
+        assert_eq!(1, simulation.conceded().iter().sum::<usize>());
+    }
+
+    #[test]
+    fn test_doubles() {
+        let settings = Settings {
+            players: 4,
+            doubles: true,
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(5, settings);
+        let sides: Vec<_> = simulation
+            .paddles
+            .iter()
+            .map(|paddle| paddle.side)
+            .collect();
+        assert_eq!(
+            vec![Side::Top, Side::Bottom, Side::Top, Side::Bottom],
+            sides
+        );
+        assert!(
+            simulation.paddles[3].position().1 < WINDOW_SIZE.1 - FRONT_OFFSET
+        );
+        let server = simulation.serve.unwrap().server;
+        assert!(server < 2);
+
+        // The front paddle of the team sends the ball back, then the ball
+        // gets past both paddles of the other team.
+        let wait = Inputs {
+            targets: vec![None; 4],
+            serve: false,
+            changes: Vec::new(),
+        };
+        simulation.step(&wait);
+        let line = simulation.paddles[2].ball_line(13.0);
+        simulation.balls[0].follow((600.0, line + 20.0), (0.0, -300.0));
+        let aside = Inputs {
+            targets: vec![None, Some(0.0), None, Some(0.0)],
+            serve: false,
+            changes: Vec::new(),
+        };
+        let mut conceded = Vec::new();
+        for _ in 0..10 {
+            conceded.extend(simulation.step(&aside));
+        }
+        assert_eq!(Some(2), simulation.balls[0].last_hit);
+        while simulation.serve.is_none() {
+            conceded.extend(simulation.step(&aside));
+        }
+        assert_eq!(vec![1], conceded);
+        assert_eq!(vec![0, 1, 0, 0], simulation.conceded());
+        assert_eq!(1, simulation.team(3));
+    }
+
+    #[test]
+    fn test_handicaps() {
+        let settings = Settings {
+            handicaps: vec![Handicap::default(), KIDS],
+            materials: Materials {
+                noise: 0.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(3, settings);
+        let widths = [simulation.paddles[0].width, simulation.paddles[1].width];
+        assert_eq!([PADDLE_SIZE.0, KIDS.paddle_width], widths);
+        let wait = Inputs {
+            targets: vec![None; 2],
+            serve: true,
+            changes: Vec::new(),
+        };
+        simulation.step(&wait);
+
+        // The ball heading towards the kid goes slower and doesn't speed up
+        // when it bounces off the wall.
+        let speed = |simulation: &Simulation| {
+            let (x, y) = simulation.balls[0].velocity();
+            x.abs().max(y.abs())
+        };
+        simulation.balls[0].follow((30.0, 200.0), (-300.0, 300.0));
+        simulation.step(&wait);
+        assert!((simulation.balls[0].pace - KIDS.ball_speed).abs() < 0.001);
+        let before = speed(&simulation);
+        for _ in 0..30 {
+            simulation.step(&wait);
+        }
+        assert!(simulation.balls[0].velocity().0 > 0.0);
+        assert!(speed(&simulation) <= before + 0.001);
+
+        // The other way it goes as fast as usual.
+        simulation.balls[0].follow((600.0, 400.0), (0.0, -300.0));
+        simulation.step(&wait);
+        assert!((simulation.balls[0].pace - 1.0).abs() < 0.001);
+
+        // Without the handicap, the kid's paddle is as wide as usual.
+        simulation.set_handicap(1, Handicap::default());
+        assert!((simulation.paddles[1].width - PADDLE_SIZE.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_breakout() {
+        let settings = Settings {
+            mode: Mode::Breakout,
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(3, settings);
+        assert!(!simulation.is_playing(0));
+        assert_eq!(SOLO_PLAYER, simulation.serve.unwrap().server);
+        let bricks = simulation.bricks.len();
+
+        // A ball sent straight up breaks the brick above it and comes back.
+        let wait = Inputs {
+            targets: vec![None; 2],
+            serve: true,
+            changes: Vec::new(),
+        };
+        simulation.step(&wait);
+        let (x, _) = simulation.bricks[bricks - 1].center;
+        simulation.balls[0].follow((x, 400.0), (0.0, -300.0));
+        while simulation.bricks.len() == bricks {
+            simulation.step(&wait);
+        }
+        for _ in 0..5 {
+            simulation.step(&wait);
+        }
+        assert_eq!(1, simulation.score());
+        assert!(simulation.balls[0].velocity().1 > 0.0);
+        assert!(!simulation.is_cleared());
+    }
+
+    #[test]
+    fn test_squash() {
+        let settings = Settings {
+            mode: Mode::Squash,
+            rally: Rally::squash(),
+            materials: Materials {
+                noise: 0.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(3, settings);
+        let wait = Inputs {
+            targets: vec![None; 2],
+            serve: true,
+            changes: Vec::new(),
+        };
+        simulation.step(&wait);
+
+        // The ball comes back off the far wall, every return counts and
+        // speeds the ball up.
+        simulation.balls[0].follow((600.0, 400.0), (0.0, -300.0));
+        let mut conceded = Vec::new();
+        while simulation.score() < 2 {
+            conceded.extend(simulation.step(&wait));
+        }
+        // The balls pick up the pace on the next tick.
+        simulation.step(&wait);
+        assert!(conceded.is_empty());
+        let pace = simulation.balls[0].pace;
+        assert!((pace - SQUASH_SPEED_UP * SQUASH_SPEED_UP).abs() < 0.001);
+        assert_eq!(2, simulation.rally_length());
+
+        let aside = Inputs {
+            targets: vec![None, Some(0.0)],
+            serve: false,
+            changes: Vec::new(),
+        };
+        while simulation.serve.is_none() {
+            conceded.extend(simulation.step(&aside));
+        }
+        assert_eq!(vec![SOLO_PLAYER], conceded);
+    }
+
+    #[test]
+    fn test_rally_speeds_balls_up() {
+        let rally = |curve: Curve| {
+            let settings = Settings {
+                rally: Rally {
+                    curve,
+                    max_pace: 2.0,
+                },
+                materials: Materials {
+                    noise: 0.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let mut simulation = Simulation::new(3, settings);
+            let wait = Inputs {
+                targets: vec![None; 2],
+                serve: true,
+                changes: Vec::new(),
+            };
+            simulation.step(&wait);
+
+            // The ball goes straight up and down between the paddles.
+            simulation.balls[0].follow((600.0, 400.0), (0.0, -300.0));
+            while simulation.rally_length() < 3 {
+                assert!(simulation.step(&wait).is_empty());
+            }
+            simulation.step(&wait);
+            simulation.balls[0].pace
+        };
+
+        assert!((rally(Curve::Linear(0.1)) - 1.3).abs() < 0.001);
+        assert!((rally(Curve::Exponential(1.1)) - 1.331).abs() < 0.001);
+        assert!((rally(Curve::Flat) - 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_four_players() {
+        let settings = Settings {
+            players: 4,
+            ..Default::default()
+        };
+        let mut simulation = Simulation::new(5, settings);
+        assert_eq!(4, simulation.paddles.len());
+        let server = simulation.serve.unwrap().server;
+        let aside = Inputs {
+            targets: vec![Some(0.0); 4],
+            serve: true,
+            changes: Vec::new(),
+        };
+
+        // The server misses the ball and the next one gets the serve.
+        let mut conceded = simulation.step(&aside);
+        while simulation.serve.is_none() {
+            conceded.extend(simulation.step(&aside));
+        }
+        assert_eq!(vec![server], conceded);
+        let next = (server + 1) % 4;
+        assert_eq!(next, simulation.serve.unwrap().server);
+
+        // Once they are out, the ball bounces off the wall in front of
+        // their goal and the serve goes to the player after them.
+        simulation.set_playing(next, false);
+        assert_eq!((next + 1) % 4, simulation.serve.unwrap().server);
+        simulation.step(&aside);
+        let mut ball = Ball::default();
+        ball.serve(next, 0.0);
+        simulation.balls[0].follow(Ball::default().center, ball.velocity());
+        for _ in 0..120 {
+            assert!(!simulation.step(&aside).contains(&next));
+        }
+        assert_eq!(0, simulation.paddles[usize::from(next)].deaths);
+    }
+}
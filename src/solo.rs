@@ -0,0 +1,130 @@
+use super::WINDOW_SIZE;
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Mesh, Rect};
+use ggez::{Context, GameResult};
+use std::env;
+
+/// The player who plays a single-player game. They sit by the bottom edge as
+/// the second player, who plays with the camera by default. The first seat
+/// stays empty and its goal is walled off.
+pub const SOLO_PLAYER: u8 = 1;
+
+/// How much faster does the ball go after each return in squash.
+pub const SQUASH_SPEED_UP: f32 = 1.05;
+
+/// How large is a brick. (width, height)
+pub const BRICK_SIZE: (f32, f32) = (100.0, 24.0);
+
+/// How many bricks are in each row and how many rows are there.
+const BRICK_COLUMNS: usize = 10;
+const BRICK_ROWS: usize = 5;
+
+/// How far apart are the bricks.
+const BRICK_GAP: f32 = 10.0;
+
+/// How far from the far edge is the first row of bricks, so that the ball
+/// can get behind the wall.
+const WALL_OFFSET: f32 = 60.0;
+
+/// What the bricks are filled with, row by row from the far edge.
+const BRICK_COLORS: [Color; BRICK_ROWS] = [
+    Color::new(0.2, 0.2, 0.2, 1.0),
+    Color::new(0.35, 0.35, 0.35, 1.0),
+    Color::new(0.5, 0.5, 0.5, 1.0),
+    Color::new(0.65, 0.65, 0.65, 1.0),
+    Color::new(0.8, 0.8, 0.8, 1.0),
+];
+
+/// What kind of game is played.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// The players play against each other.
+    Versus,
+
+    /// A lone player breaks a wall of bricks in the far half. The score is
+    /// how many bricks are broken and the game is won once they all are.
+    Breakout,
+
+    /// A lone player plays against the far wall. The score is how many
+    /// times in a row they have returned the ball, which goes faster with
+    /// every return.
+    Squash,
+}
+
+impl Mode {
+    /// Reads the kind of game from `PONG_MODE` env var, which is either
+    /// "versus" (default), "breakout" or "squash".
+    pub fn from_env() -> Self {
+        match env::var("PONG_MODE").as_ref().map(String::as_str) {
+            Err(_) | Ok("versus") => Mode::Versus,
+            Ok("breakout") => Mode::Breakout,
+            Ok("squash") => Mode::Squash,
+            Ok(other) => panic!("Unknown mode {}", other),
+        }
+    }
+
+    /// Whether one player plays alone.
+    pub fn is_solo(self) -> bool {
+        self != Mode::Versus
+    }
+}
+
+/// A brick of the wall in breakout. It breaks when a ball hits it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Brick {
+    pub center: (f32, f32),
+
+    /// Which row of the wall is the brick in, counted from the far edge.
+    pub row: usize,
+}
+
+impl Brick {
+    /// Draws the brick filled with the color of its row.
+    pub fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+        let (x, y) = self.center;
+        let (width, height) = BRICK_SIZE;
+        let mesh = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(x - width / 2.0, y - height / 2.0, width, height),
+            BRICK_COLORS[self.row],
+        )?;
+        graphics::draw(ctx, &mesh, DrawParam::default())
+    }
+}
+
+/// Lays out the wall of bricks across the far half of the playfield.
+pub fn wall() -> Vec<Brick> {
+    let (width, height) = BRICK_SIZE;
+    let length = BRICK_COLUMNS as f32 * (width + BRICK_GAP) - BRICK_GAP;
+    let left = (WINDOW_SIZE.0 - length) / 2.0;
+
+    let mut bricks = Vec::with_capacity(BRICK_COLUMNS * BRICK_ROWS);
+    for row in 0..BRICK_ROWS {
+        let y = WALL_OFFSET + row as f32 * (height + BRICK_GAP) + height / 2.0;
+        for column in 0..BRICK_COLUMNS {
+            let x = left + column as f32 * (width + BRICK_GAP) + width / 2.0;
+            bricks.push(Brick {
+                center: (x, y),
+                row,
+            });
+        }
+    }
+    bricks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wall_fills_far_half() {
+        let bricks = wall();
+        assert_eq!(BRICK_COLUMNS * BRICK_ROWS, bricks.len());
+        for brick in &bricks {
+            let (x, y) = brick.center;
+            assert!(x - BRICK_SIZE.0 / 2.0 > 0.0);
+            assert!(x + BRICK_SIZE.0 / 2.0 < WINDOW_SIZE.0);
+            assert!(y + BRICK_SIZE.1 / 2.0 < WINDOW_SIZE.1 / 2.0);
+        }
+    }
+}